@@ -0,0 +1,193 @@
+use crate::adb::error::AdbError;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const DEFAULT_PORT: u16 = 5037;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Speaks the adb server's host protocol, one request per connection
+#[derive(Clone)]
+pub struct AdbClient {
+    address: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdbDevice {
+    pub serial: String,
+    // device, offline, unauthorized, ...
+    pub state: String,
+}
+
+impl AdbDevice {
+    pub fn is_online(&self) -> bool {
+        self.state == "device"
+    }
+}
+
+impl AdbClient {
+    pub fn new(address: String) -> Self {
+        AdbClient { address }
+    }
+
+    // Honours ANDROID_ADB_SERVER_PORT like the adb binary does
+    pub fn from_env() -> Self {
+        let port = std::env::var("ANDROID_ADB_SERVER_PORT")
+            .ok()
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(DEFAULT_PORT);
+        AdbClient::new(format!("127.0.0.1:{}", port))
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.address.rsplit(':').next()?.parse().ok()
+    }
+
+    // Answers as long as a server is running
+    pub async fn version(&self) -> Result<String, AdbError> {
+        self.host_request("host:version").await
+    }
+
+    pub async fn devices(&self) -> Result<Vec<AdbDevice>, AdbError> {
+        let payload = self.host_request("host:devices").await?;
+        Ok(parse_devices(&payload))
+    }
+
+    // Returns the server's message, adb reports refused connections as a successful request
+    pub async fn connect(&self, serial: &str) -> Result<String, AdbError> {
+        self.host_request(&format!("host:connect:{}", serial)).await
+    }
+
+    pub async fn kill_server(&self) -> Result<(), AdbError> {
+        timeout(async {
+            let mut stream = TcpStream::connect(&self.address).await?;
+            send(&mut stream, "host:kill").await?;
+            read_status(&mut stream).await?;
+            // The server closes the connection as it exits
+            let _ = stream.read_to_end(&mut vec![]).await;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn shell(&self, serial: &str, command: &str) -> Result<String, AdbError> {
+        timeout(async {
+            let mut stream = TcpStream::connect(&self.address).await?;
+            send(&mut stream, &format!("host:transport:{}", serial)).await?;
+            read_status(&mut stream).await?;
+            send(&mut stream, &format!("shell:{}", command)).await?;
+            read_status(&mut stream).await?;
+            let mut output = vec![];
+            stream.read_to_end(&mut output).await?;
+            Ok(String::from_utf8_lossy(&output).into_owned())
+        })
+        .await
+    }
+
+    pub async fn reboot(&self, serial: &str) -> Result<(), AdbError> {
+        timeout(async {
+            let mut stream = TcpStream::connect(&self.address).await?;
+            send(&mut stream, &format!("host:transport:{}", serial)).await?;
+            read_status(&mut stream).await?;
+            send(&mut stream, "reboot:").await?;
+            read_status(&mut stream).await
+        })
+        .await
+    }
+
+    // Shell v1 has no exit status, so it is echoed after the output
+    pub async fn shell_status(&self, serial: &str, command: &str) -> Result<i32, AdbError> {
+        let output = self
+            .shell(serial, &format!("{} >/dev/null 2>&1; echo $?", command))
+            .await?;
+        output
+            .trim()
+            .lines()
+            .last()
+            .and_then(|status| status.trim().parse().ok())
+            .ok_or_else(|| AdbError::InvalidResponse(format!("no exit status in {:?}", output)))
+    }
+
+    async fn host_request(&self, request: &str) -> Result<String, AdbError> {
+        timeout(async {
+            let mut stream = TcpStream::connect(&self.address).await?;
+            send(&mut stream, request).await?;
+            read_status(&mut stream).await?;
+            read_payload(&mut stream).await
+        })
+        .await
+    }
+}
+
+async fn timeout<T>(
+    request: impl std::future::Future<Output = Result<T, AdbError>>,
+) -> Result<T, AdbError> {
+    tokio::time::timeout(REQUEST_TIMEOUT, request)
+        .await
+        .map_err(|_| AdbError::Timeout)?
+}
+
+async fn send(stream: &mut TcpStream, request: &str) -> Result<(), AdbError> {
+    let message = format!("{:04x}{}", request.len(), request);
+    stream.write_all(message.as_bytes()).await?;
+    Ok(())
+}
+
+async fn read_status(stream: &mut TcpStream) -> Result<(), AdbError> {
+    let mut status = [0; 4];
+    stream.read_exact(&mut status).await?;
+    match &status {
+        b"OKAY" => Ok(()),
+        b"FAIL" => Err(AdbError::Failed(read_payload(stream).await?)),
+        other => Err(AdbError::InvalidResponse(format!(
+            "unexpected status {}",
+            String::from_utf8_lossy(other)
+        ))),
+    }
+}
+
+async fn read_payload(stream: &mut TcpStream) -> Result<String, AdbError> {
+    let mut length = [0; 4];
+    stream.read_exact(&mut length).await?;
+    let length = std::str::from_utf8(&length)
+        .ok()
+        .and_then(|length| usize::from_str_radix(length, 16).ok())
+        .ok_or_else(|| AdbError::InvalidResponse("invalid payload length".to_owned()))?;
+    let mut payload = vec![0; length];
+    stream.read_exact(&mut payload).await?;
+    Ok(String::from_utf8_lossy(&payload).into_owned())
+}
+
+fn parse_devices(payload: &str) -> Vec<AdbDevice> {
+    payload
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some(AdbDevice {
+                serial: fields.next()?.to_owned(),
+                state: fields.next()?.to_owned(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_devices() {
+        let payload = "emulator-5554\tdevice\nlocalhost:40001\toffline\n\n";
+        let expected = vec![
+            AdbDevice {
+                serial: "emulator-5554".to_owned(),
+                state: "device".to_owned(),
+            },
+            AdbDevice {
+                serial: "localhost:40001".to_owned(),
+                state: "offline".to_owned(),
+            },
+        ];
+        assert_eq!(expected, parse_devices(payload));
+    }
+}
@@ -0,0 +1,32 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum AdbError {
+    Io(io::Error),
+    // The server answered FAIL with this message
+    Failed(String),
+    InvalidResponse(String),
+    Timeout,
+}
+
+impl fmt::Display for AdbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdbError::Io(e) => write!(f, "Cannot talk to the adb server: {}", e),
+            AdbError::Failed(message) => write!(f, "adb server refused: {}", message),
+            AdbError::InvalidResponse(message) => {
+                write!(f, "Invalid response from the adb server: {}", message)
+            }
+            AdbError::Timeout => write!(f, "adb server did not answer in time"),
+        }
+    }
+}
+
+impl std::error::Error for AdbError {}
+
+impl From<io::Error> for AdbError {
+    fn from(e: io::Error) -> Self {
+        AdbError::Io(e)
+    }
+}
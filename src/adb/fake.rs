@@ -0,0 +1,370 @@
+use crate::adb::AdbClient;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+// AdbClient::shell_status wraps commands in this to get at the exit status
+const STATUS_SUFFIX: &str = " >/dev/null 2>&1; echo $?";
+
+// Scripted device, its timings count from when it appears, i.e. when it is added, connected or
+// comes back from a reboot
+#[derive(Debug, Clone)]
+pub struct FakeDevice {
+    serial: String,
+    online_after: Duration,
+    booted_after: Duration,
+    // Network devices are only listed once host:connect asked for them
+    needs_connect: bool,
+    shell: HashMap<String, (String, i32)>,
+}
+
+impl FakeDevice {
+    pub fn new(serial: &str) -> Self {
+        FakeDevice {
+            serial: serial.to_owned(),
+            online_after: Duration::from_secs(0),
+            booted_after: Duration::from_secs(0),
+            needs_connect: false,
+            shell: HashMap::new(),
+        }
+    }
+
+    // Listed as offline until then
+    pub fn online_after(mut self, delay: Duration) -> Self {
+        self.online_after = delay;
+        self
+    }
+
+    // Counts from when the device appeared, not from when it went online
+    pub fn booted_after(mut self, delay: Duration) -> Self {
+        self.booted_after = delay;
+        self
+    }
+
+    pub fn needs_connect(mut self) -> Self {
+        self.needs_connect = true;
+        self
+    }
+
+    pub fn shell(mut self, command: &str, output: &str, status: i32) -> Self {
+        self.shell
+            .insert(command.to_owned(), (output.to_owned(), status));
+        self
+    }
+
+    fn run(&self, command: &str, booted: bool) -> (String, i32) {
+        if let Some(scripted) = self.shell.get(command) {
+            return scripted.clone();
+        }
+        match command {
+            "getprop sys.boot_completed" if booted => ("1\n".to_owned(), 0),
+            "getprop sys.boot_completed" => ("\n".to_owned(), 0),
+            "pm path android" if booted => (
+                "package:/system/framework/framework-res.apk\n".to_owned(),
+                0,
+            ),
+            "pm path android" => (
+                "Error: Could not access the Package Manager.  Is the system running?\n".to_owned(),
+                1,
+            ),
+            _ => (String::new(), 0),
+        }
+    }
+}
+
+struct DeviceState {
+    device: FakeDevice,
+    // Not listed while None
+    appeared_at: Option<Instant>,
+}
+
+impl DeviceState {
+    fn is_online(&self) -> bool {
+        self.appeared_at
+            .is_some_and(|appeared| appeared.elapsed() >= self.device.online_after)
+    }
+
+    fn is_booted(&self) -> bool {
+        self.appeared_at
+            .is_some_and(|appeared| appeared.elapsed() >= self.device.booted_after)
+    }
+}
+
+#[derive(Default)]
+struct ServerState {
+    devices: Vec<DeviceState>,
+    // Serial and shell command of every shell request
+    commands: Vec<(String, String)>,
+    kills: u32,
+}
+
+enum Reply {
+    // Status and payload, the connection stays open for the next request if true
+    Okay(Vec<u8>, bool),
+    Fail(String),
+}
+
+fn payload(message: &str) -> Vec<u8> {
+    format!("{:04x}{}", message.len(), message).into_bytes()
+}
+
+impl ServerState {
+    fn device(&mut self, serial: &str) -> Option<&mut DeviceState> {
+        self.devices
+            .iter_mut()
+            .find(|state| state.device.serial == serial)
+    }
+
+    fn handle(&mut self, request: &str, transport: &mut Option<String>) -> Reply {
+        if let Some(serial) = request.strip_prefix("host:connect:") {
+            return match self.device(serial) {
+                Some(state) if state.device.needs_connect => {
+                    if state.appeared_at.is_none() {
+                        state.appeared_at = Some(Instant::now());
+                    }
+                    Reply::Okay(payload(&format!("connected to {}", serial)), false)
+                }
+                _ => Reply::Okay(
+                    payload(&format!(
+                        "failed to connect to '{}': Connection refused",
+                        serial
+                    )),
+                    false,
+                ),
+            };
+        }
+        if let Some(serial) = request.strip_prefix("host:transport:") {
+            return match self.device(serial) {
+                Some(state) if state.is_online() => {
+                    *transport = Some(serial.to_owned());
+                    Reply::Okay(vec![], true)
+                }
+                Some(state) if state.appeared_at.is_some() => {
+                    Reply::Fail("device offline".to_owned())
+                }
+                _ => Reply::Fail(format!("device '{}' not found", serial)),
+            };
+        }
+        match (request, transport.clone()) {
+            ("host:version", _) => Reply::Okay(payload("0029"), false),
+            ("host:devices", _) => {
+                let listing: String = self
+                    .devices
+                    .iter()
+                    .filter(|state| state.appeared_at.is_some())
+                    .map(|state| {
+                        let status = if state.is_online() {
+                            "device"
+                        } else {
+                            "offline"
+                        };
+                        format!("{}\t{}\n", state.device.serial, status)
+                    })
+                    .collect();
+                Reply::Okay(payload(&listing), false)
+            }
+            // A restarted server has forgotten the devices it connected to
+            ("host:kill", _) => {
+                self.kills += 1;
+                for state in &mut self.devices {
+                    if state.device.needs_connect {
+                        state.appeared_at = None;
+                    }
+                }
+                Reply::Okay(vec![], false)
+            }
+            ("reboot:", Some(serial)) => {
+                let state = self.device(&serial).expect("Transport device is gone");
+                state.appeared_at = if state.device.needs_connect {
+                    None
+                } else {
+                    Some(Instant::now())
+                };
+                Reply::Okay(vec![], false)
+            }
+            (request, Some(serial)) if request.starts_with("shell:") => {
+                let command = &request["shell:".len()..];
+                self.commands.push((serial.clone(), command.to_owned()));
+                let state = self.device(&serial).expect("Transport device is gone");
+                let booted = state.is_booted();
+                let output = match command.strip_suffix(STATUS_SUFFIX) {
+                    Some(command) => format!("{}\n", state.device.run(command, booted).1),
+                    None => state.device.run(command, booted).0,
+                };
+                Reply::Okay(output.into_bytes(), false)
+            }
+            (request, _) => Reply::Fail(format!("unknown host service {}", request)),
+        }
+    }
+}
+
+// In-process stand-in for the adb server's host protocol on a random local port
+pub struct FakeAdbServer {
+    port: u16,
+    state: Arc<Mutex<ServerState>>,
+    task: JoinHandle<()>,
+}
+
+impl FakeAdbServer {
+    pub async fn start(devices: Vec<FakeDevice>) -> Self {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .expect("Cannot bind fake adb server");
+        let port = listener.local_addr().expect("No local address").port();
+        let state = Arc::new(Mutex::new(ServerState::default()));
+        let server = FakeAdbServer {
+            port,
+            state: state.clone(),
+            task: tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        let _ = serve(stream, state).await;
+                    });
+                }
+            }),
+        };
+        for device in devices {
+            server.add(device);
+        }
+        server
+    }
+
+    pub fn client(&self) -> AdbClient {
+        AdbClient::new(format!("127.0.0.1:{}", self.port))
+    }
+
+    // Plugs a device in, network devices still need a host:connect
+    pub fn add(&self, device: FakeDevice) {
+        let appeared_at = if device.needs_connect {
+            None
+        } else {
+            Some(Instant::now())
+        };
+        self.state
+            .lock()
+            .expect("Fake adb server is poisoned")
+            .devices
+            .push(DeviceState {
+                device,
+                appeared_at,
+            });
+    }
+
+    pub fn commands(&self, serial: &str) -> Vec<String> {
+        self.state
+            .lock()
+            .expect("Fake adb server is poisoned")
+            .commands
+            .iter()
+            .filter(|(device, _)| device == serial)
+            .map(|(_, command)| command.clone())
+            .collect()
+    }
+
+    pub fn kills(&self) -> u32 {
+        self.state
+            .lock()
+            .expect("Fake adb server is poisoned")
+            .kills
+    }
+}
+
+impl Drop for FakeAdbServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(mut stream: TcpStream, state: Arc<Mutex<ServerState>>) -> std::io::Result<()> {
+    let mut transport = None;
+    loop {
+        let mut length = [0; 4];
+        stream.read_exact(&mut length).await?;
+        let length = std::str::from_utf8(&length)
+            .ok()
+            .and_then(|length| usize::from_str_radix(length, 16).ok())
+            .ok_or_else(|| std::io::Error::other("invalid request length"))?;
+        let mut request = vec![0; length];
+        stream.read_exact(&mut request).await?;
+        let request = String::from_utf8_lossy(&request).into_owned();
+        let reply = state
+            .lock()
+            .expect("Fake adb server is poisoned")
+            .handle(&request, &mut transport);
+        match reply {
+            Reply::Okay(body, keep_open) => {
+                stream.write_all(b"OKAY").await?;
+                stream.write_all(&body).await?;
+                if !keep_open {
+                    return Ok(());
+                }
+            }
+            Reply::Fail(message) => {
+                stream.write_all(b"FAIL").await?;
+                stream.write_all(&payload(&message)).await?;
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adb::AdbError;
+
+    #[tokio::test]
+    async fn test_client_against_fake_server() {
+        let server = FakeAdbServer::start(vec![
+            FakeDevice::new("emulator-5554").shell("echo hi", "hi\n", 0),
+            FakeDevice::new("localhost:40001").needs_connect(),
+        ])
+        .await;
+        let adb = server.client();
+
+        let devices = adb.devices().await.expect("Cannot list devices");
+        assert_eq!(vec!["emulator-5554"], serials(&devices));
+
+        let connected = adb
+            .connect("localhost:40001")
+            .await
+            .expect("Cannot connect");
+        assert_eq!("connected to localhost:40001", connected);
+        let refused = adb
+            .connect("localhost:40002")
+            .await
+            .expect("Cannot connect");
+        assert!(refused.contains("Connection refused"));
+        assert_eq!(2, adb.devices().await.expect("Cannot list devices").len());
+
+        assert_eq!("hi\n", adb.shell("emulator-5554", "echo hi").await.unwrap());
+        assert_eq!(0, adb.shell_status("emulator-5554", "true").await.unwrap());
+        assert_eq!(
+            vec!["echo hi", "true >/dev/null 2>&1; echo $?"],
+            server.commands("emulator-5554")
+        );
+        match adb.shell("emulator-5556", "echo hi").await {
+            Err(AdbError::Failed(message)) => {
+                assert_eq!("device 'emulator-5556' not found", message)
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+
+        adb.kill_server().await.expect("Cannot kill server");
+        assert_eq!(1, server.kills());
+        let devices = adb.devices().await.expect("Cannot list devices");
+        assert_eq!(vec!["emulator-5554"], serials(&devices));
+    }
+
+    fn serials(devices: &[crate::adb::AdbDevice]) -> Vec<&str> {
+        devices
+            .iter()
+            .map(|device| device.serial.as_str())
+            .collect()
+    }
+}
@@ -0,0 +1,11 @@
+mod client;
+
+mod error;
+
+#[cfg(test)]
+mod fake;
+
+pub use client::{AdbClient, AdbDevice};
+pub use error::AdbError;
+#[cfg(test)]
+pub use fake::{FakeAdbServer, FakeDevice};
@@ -1,14 +1,23 @@
+use crate::adb::{AdbClient, AdbError};
+use std::io::ErrorKind;
 use std::process::Stdio;
 use tokio::process::Command;
 
-pub async fn restart_adb_server() -> Result<(), Box<dyn std::error::Error>> {
-    kill_adb_server().await.expect("Cannot kill adb server");
-    start_adb_server().await.expect("Cannot start adb server");
-    Ok(())
+pub async fn restart_adb_server(adb: &AdbClient) -> Result<(), Box<dyn std::error::Error>> {
+    kill_adb_server(adb).await?;
+    start_adb_server(adb).await
 }
 
-async fn start_adb_server() -> Result<(), Box<dyn std::error::Error>> {
-    Command::new("adb")
+async fn start_adb_server(adb: &AdbClient) -> Result<(), Box<dyn std::error::Error>> {
+    // Like adb start-server, nothing to do when a server answers already
+    if adb.version().await.is_ok() {
+        return Ok(());
+    }
+    let mut command = Command::new("adb");
+    if let Some(port) = adb.port() {
+        command.arg("-P").arg(port.to_string());
+    }
+    command
         .arg("start-server")
         .stdout(Stdio::null())
         .stderr(Stdio::null())
@@ -18,13 +27,10 @@ async fn start_adb_server() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn kill_adb_server() -> Result<(), Box<dyn std::error::Error>> {
-    Command::new("adb")
-        .arg("kill-server")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()?
-        .wait()
-        .await?;
-    Ok(())
+async fn kill_adb_server(adb: &AdbClient) -> Result<(), Box<dyn std::error::Error>> {
+    match adb.kill_server().await {
+        // Nothing to kill when no server is running
+        Err(AdbError::Io(e)) if e.kind() == ErrorKind::ConnectionRefused => Ok(()),
+        result => Ok(result?),
+    }
 }
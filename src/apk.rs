@@ -0,0 +1,204 @@
+use std::error::Error;
+use std::io::{self, Read};
+use std::path::Path;
+
+const CHUNK_STRING_POOL: u16 = 0x0001;
+const CHUNK_START_ELEMENT: u16 = 0x0102;
+const UTF8_FLAG: u32 = 1 << 8;
+const NO_INDEX: u32 = 0xffff_ffff;
+
+// Reads the package attribute from the binary AndroidManifest.xml inside an APK
+pub fn package_name(apk: &Path) -> Result<String, Box<dyn Error>> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(apk)?)?;
+    let mut manifest = vec![];
+    archive
+        .by_name("AndroidManifest.xml")?
+        .read_to_end(&mut manifest)?;
+    parse_package_name(&manifest)
+        .ok_or_else(|| invalid(format!("No package name in {}", apk.display())))
+}
+
+fn parse_package_name(manifest: &[u8]) -> Option<String> {
+    let mut strings = vec![];
+    // The document itself is a chunk with an 8 byte header
+    let mut offset = 8;
+    while offset + 8 <= manifest.len() {
+        let chunk_type = u16_at(manifest, offset)?;
+        let header_size = u16_at(manifest, offset + 2)? as usize;
+        let size = u32_at(manifest, offset + 4)? as usize;
+        let chunk = manifest.get(offset..offset + size)?;
+        match chunk_type {
+            CHUNK_STRING_POOL => strings = parse_string_pool(chunk)?,
+            CHUNK_START_ELEMENT => {
+                let name = u32_at(chunk, 20)?;
+                if strings.get(name as usize).map(String::as_str) == Some("manifest") {
+                    return package_attribute(chunk, header_size, &strings);
+                }
+            }
+            _ => {}
+        }
+        if size == 0 {
+            return None;
+        }
+        offset += size;
+    }
+    None
+}
+
+fn package_attribute(chunk: &[u8], header_size: usize, strings: &[String]) -> Option<String> {
+    let attribute_start = u16_at(chunk, header_size + 8)? as usize;
+    let attribute_size = u16_at(chunk, header_size + 10)? as usize;
+    let attribute_count = u16_at(chunk, header_size + 12)? as usize;
+    (0..attribute_count)
+        .map(|idx| header_size + attribute_start + idx * attribute_size)
+        .find(|attribute| {
+            u32_at(chunk, attribute + 4)
+                .and_then(|name| strings.get(name as usize))
+                .map(String::as_str)
+                == Some("package")
+        })
+        .and_then(|attribute| {
+            let raw_value = u32_at(chunk, attribute + 8)?;
+            let value = if raw_value == NO_INDEX {
+                u32_at(chunk, attribute + 16)?
+            } else {
+                raw_value
+            };
+            strings.get(value as usize).cloned()
+        })
+}
+
+fn parse_string_pool(chunk: &[u8]) -> Option<Vec<String>> {
+    let count = u32_at(chunk, 8)? as usize;
+    let flags = u32_at(chunk, 16)?;
+    let strings_start = u32_at(chunk, 20)? as usize;
+    let header_size = u16_at(chunk, 2)? as usize;
+    (0..count)
+        .map(|idx| {
+            let offset = strings_start + u32_at(chunk, header_size + idx * 4)? as usize;
+            if flags & UTF8_FLAG != 0 {
+                utf8_string(chunk, offset)
+            } else {
+                utf16_string(chunk, offset)
+            }
+        })
+        .collect()
+}
+
+// Lengths take one byte, two with the high bit set. Character count first, then byte count
+fn utf8_string(chunk: &[u8], offset: usize) -> Option<String> {
+    let (_, offset) = utf8_length(chunk, offset)?;
+    let (length, offset) = utf8_length(chunk, offset)?;
+    let bytes = chunk.get(offset..offset + length)?;
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn utf8_length(chunk: &[u8], offset: usize) -> Option<(usize, usize)> {
+    let first = *chunk.get(offset)? as usize;
+    if first & 0x80 != 0 {
+        let second = *chunk.get(offset + 1)? as usize;
+        Some((((first & 0x7f) << 8) | second, offset + 2))
+    } else {
+        Some((first, offset + 1))
+    }
+}
+
+fn utf16_string(chunk: &[u8], offset: usize) -> Option<String> {
+    let first = u16_at(chunk, offset)? as usize;
+    let (length, offset) = if first & 0x8000 != 0 {
+        let second = u16_at(chunk, offset + 2)? as usize;
+        (((first & 0x7fff) << 16) | second, offset + 4)
+    } else {
+        (first, offset + 2)
+    };
+    let units: Option<Vec<u16>> = (0..length)
+        .map(|idx| u16_at(chunk, offset + idx * 2))
+        .collect();
+    Some(String::from_utf16_lossy(&units?))
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn invalid(message: String) -> Box<dyn Error> {
+    Box::new(io::Error::new(io::ErrorKind::InvalidData, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16_pool(strings: &[&str]) -> Vec<u8> {
+        let mut data = vec![];
+        let mut offsets = vec![];
+        for string in strings {
+            offsets.push(data.len() as u32);
+            let units: Vec<u16> = string.encode_utf16().collect();
+            data.extend_from_slice(&(units.len() as u16).to_le_bytes());
+            units
+                .iter()
+                .for_each(|unit| data.extend_from_slice(&unit.to_le_bytes()));
+            data.extend_from_slice(&[0, 0]);
+        }
+        let strings_start = 28 + offsets.len() * 4;
+        let mut chunk = vec![];
+        chunk.extend_from_slice(&CHUNK_STRING_POOL.to_le_bytes());
+        chunk.extend_from_slice(&28u16.to_le_bytes());
+        chunk.extend_from_slice(&((strings_start + data.len()) as u32).to_le_bytes());
+        chunk.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(&0u32.to_le_bytes());
+        chunk.extend_from_slice(&0u32.to_le_bytes());
+        chunk.extend_from_slice(&(strings_start as u32).to_le_bytes());
+        chunk.extend_from_slice(&0u32.to_le_bytes());
+        offsets
+            .iter()
+            .for_each(|offset| chunk.extend_from_slice(&offset.to_le_bytes()));
+        chunk.extend_from_slice(&data);
+        chunk
+    }
+
+    // <manifest versionCode=... package="com.example.app">
+    fn manifest_element() -> Vec<u8> {
+        let mut chunk = vec![];
+        chunk.extend_from_slice(&CHUNK_START_ELEMENT.to_le_bytes());
+        chunk.extend_from_slice(&16u16.to_le_bytes());
+        chunk.extend_from_slice(&(36u32 + 2 * 20).to_le_bytes());
+        chunk.extend_from_slice(&1u32.to_le_bytes());
+        chunk.extend_from_slice(&NO_INDEX.to_le_bytes());
+        chunk.extend_from_slice(&NO_INDEX.to_le_bytes());
+        chunk.extend_from_slice(&0u32.to_le_bytes());
+        for value in &[20u16, 20, 2, 0, 0, 0] {
+            chunk.extend_from_slice(&value.to_le_bytes());
+        }
+        for (name, raw_value) in &[(1u32, NO_INDEX), (2, 3)] {
+            chunk.extend_from_slice(&NO_INDEX.to_le_bytes());
+            chunk.extend_from_slice(&name.to_le_bytes());
+            chunk.extend_from_slice(&raw_value.to_le_bytes());
+            chunk.extend_from_slice(&[8, 0, 0, 0x10, 1, 0, 0, 0]);
+        }
+        chunk
+    }
+
+    #[test]
+    fn test_parse_package_name() {
+        let pool = utf16_pool(&["manifest", "versionCode", "package", "com.example.app"]);
+        let element = manifest_element();
+        let mut manifest = vec![];
+        manifest.extend_from_slice(&0x0003u16.to_le_bytes());
+        manifest.extend_from_slice(&8u16.to_le_bytes());
+        manifest.extend_from_slice(&((8 + pool.len() + element.len()) as u32).to_le_bytes());
+        manifest.extend_from_slice(&pool);
+        manifest.extend_from_slice(&element);
+        assert_eq!(
+            Some("com.example.app".to_owned()),
+            parse_package_name(&manifest)
+        );
+    }
+}
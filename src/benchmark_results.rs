@@ -1,4 +1,11 @@
+use crate::config::NetworkProfile;
+use crate::devices::{DeviceTiming, Disturbance};
+use crate::junit::TestResults;
+use crate::network::InjectedFault;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
 use std::time::{Duration, Instant};
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -6,14 +13,163 @@ pub struct ExecutionReport {
     suite_name: String,
     iteration: u32,
     duration: Duration,
+    #[serde(default)]
+    tests: Option<TestResults>,
+    // Why the runner's JUnit reports could not be read, tests stay empty then
+    #[serde(default)]
+    junit_error: Option<String>,
+    #[serde(default)]
+    exit: Option<RunnerExit>,
+    // Invalid iterations are kept on disk but excluded from statistics
+    #[serde(default = "default_valid")]
+    valid: bool,
+    #[serde(default)]
+    provisioning: Option<Provisioning>,
+    // Shaping applied to the adb link, results are only comparable under the same profile
+    #[serde(default)]
+    network: Option<NetworkProfile>,
+    // Faults injected into device links while tests ran, to match against failures and retries
+    #[serde(default)]
+    faults: Vec<InjectedFault>,
+    #[serde(default)]
+    chaos: Option<ChaosReport>,
+}
+
+// Devices chaos mode stopped or paused while tests ran
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChaosReport {
+    pub baseline: Option<String>,
+    pub disturbances: Vec<Disturbance>,
+}
+
+// Time spent bringing devices up before the runner started, kept apart from the test run
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Provisioning {
+    pub duration: Duration,
+    pub devices: Vec<DeviceTiming>,
+    // Devices were kept from an earlier iteration and only reset
+    #[serde(default)]
+    pub reused: bool,
+}
+
+impl Provisioning {
+    pub fn new(duration: Duration, devices: Vec<DeviceTiming>, reused: bool) -> Self {
+        Provisioning {
+            duration,
+            devices,
+            reused,
+        }
+    }
+}
+
+fn default_valid() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RunnerExit {
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+}
+
+impl RunnerExit {
+    pub fn new(status: ExitStatus) -> Self {
+        RunnerExit {
+            code: status.code(),
+            signal: status.signal(),
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+impl fmt::Display for RunnerExit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.code, self.signal) {
+            (Some(code), _) => write!(f, "exit code {}", code),
+            (None, Some(signal)) => write!(f, "killed by signal {}", signal),
+            (None, None) => write!(f, "unknown exit status"),
+        }
+    }
 }
 
 impl ExecutionReport {
-    pub fn new(suite_name: String, iteration: u32, start_time: Instant, end_time: Instant) -> Self {
+    pub fn new(
+        suite_name: String,
+        iteration: u32,
+        start_time: Instant,
+        end_time: Instant,
+        tests: Option<TestResults>,
+        exit: Option<RunnerExit>,
+    ) -> Self {
         ExecutionReport {
             suite_name,
             iteration,
             duration: end_time - start_time,
+            tests,
+            junit_error: None,
+            valid: exit.as_ref().map(|e| e.success()).unwrap_or(false),
+            exit,
+            provisioning: None,
+            network: None,
+            faults: vec![],
+            chaos: None,
         }
     }
+
+    pub fn with_provisioning(mut self, provisioning: Provisioning) -> Self {
+        self.provisioning = Some(provisioning);
+        self
+    }
+
+    pub fn with_junit_error(mut self, junit_error: Option<String>) -> Self {
+        self.junit_error = junit_error;
+        self
+    }
+
+    pub fn with_faults(mut self, faults: Vec<InjectedFault>) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    pub fn with_chaos(mut self, chaos: Option<ChaosReport>) -> Self {
+        self.chaos = chaos;
+        self
+    }
+
+    pub fn with_network(mut self, network: Option<NetworkProfile>) -> Self {
+        self.network = network;
+        self
+    }
+
+    pub fn suite_name(&self) -> &str {
+        &self.suite_name
+    }
+
+    pub fn iteration(&self) -> u32 {
+        self.iteration
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+
+    pub fn tests(&self) -> Option<&TestResults> {
+        self.tests.as_ref()
+    }
+
+    // The runner finished successfully
+    pub fn succeeded(&self) -> bool {
+        self.exit.is_some_and(|exit| exit.success())
+    }
+
+    pub fn chaos(&self) -> Option<&ChaosReport> {
+        self.chaos.as_ref()
+    }
 }
@@ -1,6 +1,24 @@
 mod types;
+mod validation;
 
+pub use types::Chaos;
+pub use types::ChaosAction;
+pub use types::Comparison;
 pub use types::Config;
+pub use types::ContainerResources;
 pub use types::DeviceProvider;
+pub use types::DeviceReset;
+pub use types::EmulatorOptions;
+pub use types::EmulatorPorts;
+pub use types::FailurePolicy;
+pub use types::Fault;
+pub use types::FaultInjection;
+pub use types::FaultRule;
+pub use types::History;
+pub use types::NetworkProfile;
+pub use types::PoolScope;
+pub use types::Readiness;
+pub use types::SimulatedDurations;
 pub use types::TestRunner;
 pub use types::TestSuite;
+pub use validation::validate;
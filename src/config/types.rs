@@ -3,9 +3,148 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     pub input: Input,
+    #[serde(default)]
+    pub monitoring: Monitoring,
+    #[serde(default)]
+    pub comparison: Comparison,
+    pub history: Option<History>,
+    #[serde(default)]
+    pub readiness: Readiness,
+    #[serde(default)]
+    pub device_pool: DevicePooling,
     pub test_suites: Vec<TestSuite>,
 }
 
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Monitoring {
+    #[serde(default = "default_monitoring_interval_ms")]
+    pub interval_ms: u64,
+}
+
+impl Default for Monitoring {
+    fn default() -> Self {
+        Monitoring {
+            interval_ms: default_monitoring_interval_ms(),
+        }
+    }
+}
+
+fn default_monitoring_interval_ms() -> u64 {
+    1000
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Comparison {
+    #[serde(default = "default_alpha")]
+    pub alpha: f64,
+    #[serde(default = "default_bootstrap_resamples")]
+    pub bootstrap_resamples: u32,
+}
+
+impl Default for Comparison {
+    fn default() -> Self {
+        Comparison {
+            alpha: default_alpha(),
+            bootstrap_resamples: default_bootstrap_resamples(),
+        }
+    }
+}
+
+fn default_alpha() -> f64 {
+    0.05
+}
+
+fn default_bootstrap_resamples() -> u32 {
+    10000
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct History {
+    #[serde(default = "default_history_database")]
+    pub database: String,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            database: default_history_database(),
+        }
+    }
+}
+
+fn default_history_database() -> String {
+    "history.sqlite".to_owned()
+}
+
+// How long devices live. Suites share a run scoped pool when their providers and emulator
+// counts are equal
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct DevicePooling {
+    #[serde(default)]
+    pub scope: PoolScope,
+    // Shell commands run on every device before it is reused
+    #[serde(default = "default_pool_reset")]
+    pub reset: Vec<String>,
+}
+
+impl Default for DevicePooling {
+    fn default() -> Self {
+        DevicePooling {
+            scope: PoolScope::default(),
+            reset: default_pool_reset(),
+        }
+    }
+}
+
+fn default_pool_reset() -> Vec<String> {
+    vec!["logcat -c".to_owned()]
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolScope {
+    #[default]
+    Iteration,
+    Suite,
+    Run,
+}
+
+// How long and how often to poll devices before a runner may use them
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Readiness {
+    #[serde(default = "default_readiness_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_readiness_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "default_readiness_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    // Shell command run on every device, the device is ready once it exits with 0
+    pub probe: Option<String>,
+}
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Readiness {
+            timeout_secs: default_readiness_timeout_secs(),
+            initial_backoff_ms: default_readiness_initial_backoff_ms(),
+            max_backoff_ms: default_readiness_max_backoff_ms(),
+            probe: None,
+        }
+    }
+}
+
+fn default_readiness_timeout_secs() -> u64 {
+    300
+}
+
+fn default_readiness_initial_backoff_ms() -> u64 {
+    500
+}
+
+fn default_readiness_max_backoff_ms() -> u64 {
+    5000
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Input {
     pub apk: String,
@@ -15,11 +154,80 @@ pub struct Input {
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum DeviceProvider {
-    Docker { image: String, tag: String },
-    LocalEmulator { name: String },
+    Docker {
+        image: String,
+        tag: String,
+        // Containers started and connected at the same time
+        #[serde(default = "default_docker_concurrency")]
+        concurrency: usize,
+        #[serde(default)]
+        resources: ContainerResources,
+    },
+    // Same images as docker, rootless podman needs access to /dev/kvm through the kvm group
+    Podman {
+        image: String,
+        tag: String,
+        #[serde(default = "default_docker_concurrency")]
+        concurrency: usize,
+        #[serde(default)]
+        rootless: bool,
+        #[serde(default)]
+        resources: ContainerResources,
+    },
+    LocalEmulator {
+        name: String,
+        #[serde(flatten)]
+        options: EmulatorOptions,
+    },
     AvailableOnly,
 }
 
+fn default_docker_concurrency() -> usize {
+    4
+}
+
+// Limits applied to every emulator container, sizes take k, m and g suffixes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContainerResources {
+    pub cpus: Option<f64>,
+    pub cpuset: Option<String>,
+    pub memory: Option<String>,
+    pub shm_size: Option<String>,
+    // Gives each container its own cores, overrides cpuset
+    pub cpu_pinning: Option<CpuPinning>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CpuPinning {
+    // The first cores are left to the runner JVM and the benchmark itself
+    #[serde(default = "default_reserved_cores")]
+    pub reserved_cores: u32,
+}
+
+fn default_reserved_cores() -> u32 {
+    2
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmulatorOptions {
+    // Snapshot to boot from instead of the default boot
+    pub snapshot: Option<String>,
+    #[serde(default)]
+    pub no_snapshot_save: bool,
+    pub gpu: Option<String>,
+    pub memory_mb: Option<u32>,
+    pub cores: Option<u32>,
+    // One pair per emulator, defaults to 5554/5555, 5556/5557 and so on
+    #[serde(default)]
+    pub ports: Vec<EmulatorPorts>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EmulatorPorts {
+    pub console: u16,
+    pub adb: u16,
+}
+
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum TestRunner {
@@ -37,6 +245,45 @@ pub enum TestRunner {
     Spoon2 {
         program: String,
     },
+    // Stands in for a real runner without any Android tooling, sleeps and writes made up
+    // JUnit results
+    Simulated {
+        durations: SimulatedDurations,
+        #[serde(default = "default_simulated_tests")]
+        tests: u32,
+        #[serde(default)]
+        failing_tests: u32,
+        // Fail once and pass on the retry
+        #[serde(default)]
+        flaky_tests: u32,
+        #[serde(default)]
+        exit_code: i32,
+        seed: Option<u64>,
+    },
+}
+
+fn default_simulated_tests() -> u32 {
+    10
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SimulatedDurations {
+    // Milliseconds used by iterations in order, starting over when they run out
+    Scripted(Vec<u64>),
+    Normal { mean_ms: u64, std_dev_ms: u64 },
+}
+
+// What to do with an iteration whose runner exited with a non-zero status
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    Retry {
+        attempts: u32,
+    },
+    #[default]
+    MarkInvalid,
+    Abort,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -46,4 +293,98 @@ pub struct TestSuite {
     pub emulators: u32,
     pub device_provider: DeviceProvider,
     pub test_runner: TestRunner,
+    #[serde(default)]
+    pub on_failure: FailurePolicy,
+    // Runs on reused devices once they are ready, after the pool's reset commands
+    pub reset: Option<DeviceReset>,
+    // Shapes the adb link to container devices
+    pub network: Option<NetworkProfile>,
+    // Misbehaving adb links of container devices while tests run
+    pub faults: Option<FaultInjection>,
+    // Stops or pauses devices while tests run
+    pub chaos: Option<Chaos>,
+}
+
+// Applied in both directions. Loss is emulated as retransmission delays since adb runs over TCP
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetworkProfile {
+    #[serde(default)]
+    pub latency_ms: u64,
+    #[serde(default)]
+    pub jitter_ms: u64,
+    pub bandwidth_kbps: Option<u64>,
+    #[serde(default)]
+    pub loss_percent: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FaultInjection {
+    // Random faults hit the same places in every iteration when set
+    pub seed: Option<u64>,
+    pub rules: Vec<FaultRule>,
+}
+
+// Fires once at_secs after the test run started, or with probability for every chunk forwarded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FaultRule {
+    pub fault: Fault,
+    pub at_secs: Option<u64>,
+    pub probability: Option<f64>,
+    // Indices of the devices the rule applies to, all of them when empty
+    #[serde(default)]
+    pub devices: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fault {
+    DropConnections,
+    StallReads { duration_ms: u64 },
+    // Connections are cut and refused for the duration, until the test run ends without one
+    Disconnect { duration_secs: Option<u64> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chaos {
+    // Suite running the same tests undisturbed, lost tests and extra time are relative to it
+    pub baseline: Option<String>,
+    // Picks the same devices in every iteration when set
+    pub seed: Option<u64>,
+    pub events: Vec<ChaosEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChaosEvent {
+    // Seconds after the test run started
+    pub at_secs: u64,
+    pub action: ChaosAction,
+    // Number of devices hit, picked at random among those still running
+    #[serde(default = "default_chaos_devices")]
+    pub devices: u32,
+}
+
+fn default_chaos_devices() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChaosAction {
+    Stop,
+    Pause { duration_secs: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceReset {
+    // Applies to the packages of both input APKs
+    #[serde(default)]
+    pub uninstall: bool,
+    #[serde(default)]
+    pub clear_data: bool,
+    #[serde(default)]
+    pub sdcard_paths: Vec<String>,
+    #[serde(default)]
+    pub reboot: bool,
+    // Loaded through the emulator console, local emulators only
+    pub snapshot: Option<String>,
 }
@@ -0,0 +1,277 @@
+use crate::config::{Config, DeviceProvider};
+use crate::network;
+use std::error::Error;
+
+// Catches mistakes in the config before any device is started
+pub fn validate(config: &Config) -> Result<(), Box<dyn Error>> {
+    // Samplers tick at this interval, a zero one cannot tick at all
+    if config.monitoring.interval_ms == 0 {
+        return Err(invalid(
+            "Monitoring interval_ms has to be above 0".to_owned(),
+        ));
+    }
+    let readiness = &config.readiness;
+    if readiness.initial_backoff_ms == 0 || readiness.max_backoff_ms < readiness.initial_backoff_ms
+    {
+        return Err(invalid(format!(
+            "Readiness backoff has to start above 0 and grow up to max_backoff_ms, got {} to {}",
+            readiness.initial_backoff_ms, readiness.max_backoff_ms
+        )));
+    }
+    let alpha = config.comparison.alpha;
+    if !(alpha > 0.0 && alpha < 1.0) {
+        return Err(invalid(format!(
+            "Comparison alpha has to be between 0 and 1, got {}",
+            alpha
+        )));
+    }
+    for suite in &config.test_suites {
+        if (suite.network.is_some() || suite.faults.is_some())
+            && !supports_network(&suite.device_provider)
+        {
+            return Err(invalid(format!(
+                "Suite {} shapes device links but its devices are not containers",
+                suite.name
+            )));
+        }
+        if let Some(faults) = &suite.faults {
+            if !faults.rules.iter().all(network::is_valid) {
+                return Err(invalid(format!(
+                    "Fault rules of suite {} need either at_secs or a probability up to 1",
+                    suite.name
+                )));
+            }
+        }
+        let snapshot = suite
+            .reset
+            .as_ref()
+            .and_then(|reset| reset.snapshot.as_ref());
+        // Snapshots are loaded through the emulator console, only local emulators expose it
+        if snapshot.is_some()
+            && !matches!(suite.device_provider, DeviceProvider::LocalEmulator { .. })
+        {
+            return Err(invalid(format!(
+                "Suite {} resets to a snapshot but its devices are not local emulators",
+                suite.name
+            )));
+        }
+        if let Some(chaos) = &suite.chaos {
+            if !supports_chaos(&suite.device_provider) {
+                return Err(invalid(format!(
+                    "Suite {} uses chaos mode but its devices cannot be stopped",
+                    suite.name
+                )));
+            }
+            if let Some(baseline) = &chaos.baseline {
+                let baseline = config
+                    .test_suites
+                    .iter()
+                    .find(|other| &other.name == baseline && other.name != suite.name)
+                    .ok_or_else(|| {
+                        invalid(format!(
+                            "Chaos baseline of suite {} has to be another suite",
+                            suite.name
+                        ))
+                    })?;
+                // Lost tests and extra time only mean something when both ran the same tests
+                if baseline.test_runner != suite.test_runner {
+                    return Err(invalid(format!(
+                        "Chaos baseline {} of suite {} runs other tests",
+                        baseline.name, suite.name
+                    )));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn supports_network(provider: &DeviceProvider) -> bool {
+    matches!(
+        provider,
+        DeviceProvider::Docker { .. } | DeviceProvider::Podman { .. }
+    )
+}
+
+fn supports_chaos(provider: &DeviceProvider) -> bool {
+    supports_network(provider) || matches!(provider, DeviceProvider::LocalEmulator { .. })
+}
+
+fn invalid(message: String) -> Box<dyn Error> {
+    Box::new(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        message,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(suites: &str) -> Config {
+        let yaml = format!(
+            "input:\n  apk: app.apk\n  test_apk: app-test.apk\ntest_suites:\n{}",
+            suites
+        );
+        serde_yaml::from_str(&yaml).expect("Config does not parse")
+    }
+
+    const SUITE: &str = r#"
+  - name: suite
+    iterations: 1
+    emulators: 1
+    device_provider: available_only
+    test_runner:
+      spoon2:
+        program: spoon
+"#;
+
+    #[test]
+    fn test_alpha_is_a_probability() {
+        let mut valid = config(SUITE);
+        assert!(validate(&valid).is_ok());
+        for alpha in &[0.0, 1.0, -0.5, f64::NAN] {
+            valid.comparison.alpha = *alpha;
+            let error = validate(&valid).expect_err("Alpha outside (0, 1) is valid");
+            assert!(error.to_string().contains("Comparison alpha"));
+        }
+    }
+
+    #[test]
+    fn test_monitoring_interval_is_positive() {
+        let mut zero = config(SUITE);
+        zero.monitoring.interval_ms = 0;
+        let error = validate(&zero).expect_err("Zero monitoring interval is valid");
+        assert!(error.to_string().contains("interval_ms"));
+    }
+
+    #[test]
+    fn test_readiness_backoff_grows() {
+        let mut readiness = config(SUITE);
+        for (initial, max) in &[(0, 100), (200, 100)] {
+            readiness.readiness.initial_backoff_ms = *initial;
+            readiness.readiness.max_backoff_ms = *max;
+            let error = validate(&readiness).expect_err("Backoff is valid");
+            assert!(error.to_string().contains("Readiness backoff"));
+        }
+        readiness.readiness.initial_backoff_ms = 100;
+        assert!(validate(&readiness).is_ok());
+    }
+
+    #[test]
+    fn test_network_needs_containers() {
+        let suite = r#"
+  - name: shaped
+    iterations: 1
+    emulators: 1
+    device_provider: {provider}
+    test_runner:
+      spoon2:
+        program: spoon
+    network:
+      latency_ms: 100
+"#;
+        let local = config(&suite.replace("{provider}", "available_only"));
+        let error = validate(&local).expect_err("Network on available devices is valid");
+        assert!(error.to_string().contains("Suite shaped"));
+
+        let docker = suite.replace(
+            "{provider}",
+            "\n      docker:\n        image: emulator\n        tag: latest",
+        );
+        assert!(validate(&config(&docker)).is_ok());
+    }
+
+    #[test]
+    fn test_fault_rules_need_one_trigger() {
+        let suite = r#"
+  - name: faulty
+    iterations: 1
+    emulators: 1
+    device_provider:
+      docker:
+        image: emulator
+        tag: latest
+    test_runner:
+      spoon2:
+        program: spoon
+    faults:
+      rules:
+        - fault: drop_connections
+          at_secs: 10
+          probability: 0.5
+"#;
+        let error = validate(&config(suite)).expect_err("Fault rule with two triggers is valid");
+        assert!(error.to_string().contains("Fault rules of suite faulty"));
+    }
+
+    #[test]
+    fn test_chaos_baseline_runs_the_same_tests() {
+        let suites = r#"
+  - name: baseline
+    iterations: 1
+    emulators: 1
+    device_provider:
+      docker:
+        image: emulator
+        tag: latest
+    test_runner:
+      spoon2:
+        program: {program}
+  - name: chaos
+    iterations: 1
+    emulators: 1
+    device_provider:
+      docker:
+        image: emulator
+        tag: latest
+    test_runner:
+      spoon2:
+        program: spoon
+    chaos:
+      baseline: {baseline}
+      events:
+        - at_secs: 5
+          action: stop
+"#;
+        let valid = suites
+            .replace("{program}", "spoon")
+            .replace("{baseline}", "baseline");
+        assert!(validate(&config(&valid)).is_ok());
+
+        let other_runner = suites
+            .replace("{program}", "other")
+            .replace("{baseline}", "baseline");
+        let error = validate(&config(&other_runner)).expect_err("Baseline runs other tests");
+        assert!(error.to_string().contains("runs other tests"));
+
+        for baseline in &["chaos", "missing"] {
+            let unknown = suites
+                .replace("{program}", "spoon")
+                .replace("{baseline}", baseline);
+            let error = validate(&config(&unknown)).expect_err("Baseline is not another suite");
+            assert!(error.to_string().contains("has to be another suite"));
+        }
+    }
+
+    #[test]
+    fn test_reset_snapshot_needs_local_emulators() {
+        let suite = r#"
+  - name: snapshot
+    iterations: 1
+    emulators: 1
+    device_provider: {provider}
+    test_runner:
+      spoon2:
+        program: spoon
+    reset:
+      snapshot: clean
+"#;
+        let available = config(&suite.replace("{provider}", "available_only"));
+        let error = validate(&available).expect_err("Snapshot on available devices is valid");
+        assert!(error.to_string().contains("resets to a snapshot"));
+
+        let local = suite.replace("{provider}", "\n      local_emulator:\n        name: Pixel");
+        assert!(validate(&config(&local)).is_ok());
+    }
+}
@@ -1,5 +1,6 @@
-use crate::devices::provider::Provider;
+use crate::devices::provider::{DeviceContext, Disruption, Provider};
 use async_trait::async_trait;
+use std::io::{Error, ErrorKind};
 
 pub struct AvailableOnlyProvider {}
 
@@ -11,13 +12,31 @@ impl AvailableOnlyProvider {
 
 #[async_trait]
 impl Provider for AvailableOnlyProvider {
-    async fn prepare(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    async fn prepare(
+        &mut self,
+        _context: &DeviceContext,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         Ok(())
     }
-    async fn connect(&self) -> Result<(), Box<dyn std::error::Error>> {
+    async fn connect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         Ok(())
     }
     async fn terminate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         Ok(())
     }
+    // Devices which are not ours are never touched
+    async fn disrupt(
+        &mut self,
+        _device: usize,
+        _disruption: Disruption,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Err(Box::new(Error::new(
+            ErrorKind::Unsupported,
+            "Available devices cannot be disrupted",
+        )))
+    }
+    fn kill(&mut self) {}
+    fn serials(&self) -> Option<Vec<String>> {
+        None
+    }
 }
@@ -0,0 +1,146 @@
+use crate::config::{Chaos, ChaosAction};
+use crate::devices::pool::DevicePool;
+use crate::devices::provider::Disruption;
+use crate::random::XorShift;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+// What chaos mode did to one device, offsets are relative to the start of the test run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Disturbance {
+    pub serial: String,
+    pub after: Duration,
+    pub disruption: Disruption,
+    pub error: Option<String>,
+}
+
+enum Step {
+    Event(usize),
+    Resume(usize),
+}
+
+// Stops and pauses devices at the configured offsets while the runner is going
+pub struct ChaosSchedule {
+    chaos: Chaos,
+    started_at: Instant,
+    paused: HashSet<usize>,
+    disturbances: Vec<Disturbance>,
+}
+
+impl ChaosSchedule {
+    pub fn new(chaos: Chaos) -> Self {
+        ChaosSchedule {
+            chaos,
+            started_at: Instant::now(),
+            paused: HashSet::new(),
+            disturbances: vec![],
+        }
+    }
+
+    pub fn baseline(&self) -> Option<String> {
+        self.chaos.baseline.clone()
+    }
+
+    // Returns once every event happened, the caller drops it when the runner exits first.
+    // Disturbances are kept as they happen so nothing is lost on cancellation
+    pub async fn run(&mut self, devices: &Mutex<DevicePool>) {
+        self.started_at = Instant::now();
+        self.paused.clear();
+        self.disturbances.clear();
+        let mut rng = match self.chaos.seed {
+            Some(seed) => XorShift::new(seed),
+            None => XorShift::from_time(),
+        };
+        let mut stopped = HashSet::new();
+        let mut steps: Vec<(Duration, Step)> = self
+            .chaos
+            .events
+            .iter()
+            .enumerate()
+            .map(|(idx, event)| (Duration::from_secs(event.at_secs), Step::Event(idx)))
+            .collect();
+        while let Some(next) = earliest(&steps) {
+            let (after, step) = steps.remove(next);
+            tokio::time::sleep_until(self.started_at + after).await;
+            let mut pool = devices.lock().await;
+            let serials = pool.serials().unwrap_or_default();
+            match step {
+                Step::Event(idx) => {
+                    let event = self.chaos.events[idx].clone();
+                    let mut running: Vec<usize> = (0..serials.len())
+                        .filter(|device| !stopped.contains(device) && !self.paused.contains(device))
+                        .collect();
+                    for _ in 0..event.devices {
+                        if running.is_empty() {
+                            break;
+                        }
+                        let device = running.swap_remove(rng.below(running.len()));
+                        let disruption = match event.action {
+                            ChaosAction::Stop => {
+                                stopped.insert(device);
+                                Disruption::Stop
+                            }
+                            ChaosAction::Pause { duration_secs } => {
+                                self.paused.insert(device);
+                                let resume_after = after + Duration::from_secs(duration_secs);
+                                steps.push((resume_after, Step::Resume(device)));
+                                Disruption::Pause
+                            }
+                        };
+                        self.disturb(&mut pool, &serials, device, disruption).await;
+                    }
+                }
+                Step::Resume(device) => {
+                    self.paused.remove(&device);
+                    self.disturb(&mut pool, &serials, device, Disruption::Resume)
+                        .await;
+                }
+            }
+        }
+    }
+
+    // Paused devices would block terminating them, resume them before handing the log out
+    pub async fn settle(&mut self, devices: &Mutex<DevicePool>) -> Vec<Disturbance> {
+        let mut pool = devices.lock().await;
+        let serials = pool.serials().unwrap_or_default();
+        let mut paused: Vec<usize> = self.paused.drain().collect();
+        paused.sort_unstable();
+        for device in paused {
+            self.disturb(&mut pool, &serials, device, Disruption::Resume)
+                .await;
+        }
+        std::mem::take(&mut self.disturbances)
+    }
+
+    async fn disturb(
+        &mut self,
+        pool: &mut DevicePool,
+        serials: &[String],
+        device: usize,
+        disruption: Disruption,
+    ) {
+        let error = pool
+            .disrupt(device, disruption)
+            .await
+            .err()
+            .map(|e| e.to_string());
+        self.disturbances.push(Disturbance {
+            serial: serials[device].clone(),
+            after: self.started_at.elapsed(),
+            disruption,
+            error,
+        });
+    }
+}
+
+// Ties keep the configured order
+fn earliest(steps: &[(Duration, Step)]) -> Option<usize> {
+    steps
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, (after, _))| *after)
+        .map(|(idx, _)| idx)
+}
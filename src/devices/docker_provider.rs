@@ -1,132 +1,506 @@
-use crate::devices::provider::Provider;
+use crate::adb::AdbClient;
+use crate::config::{ContainerResources, FaultInjection, NetworkProfile};
+use crate::devices::provider::{DeviceContext, DeviceTiming, Disruption, Provider};
+use crate::devices::resources::container_resources;
+use crate::docker::{ContainerSpec, DockerClient, DockerError, Resources};
+use crate::network::{DeviceFaults, FaultLog, InjectedFault, LinkProxy};
 use async_trait::async_trait;
 use port_scanner::request_open_port;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::{Error, ErrorKind};
-use std::process::Stdio;
-use std::time::Duration;
-use tokio::process::Command;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+// Every container carries these so leftovers of crashed runs can be found later
+pub const RUN_LABEL: &str = "marathon-benchmark.run";
+pub const SUITE_LABEL: &str = "marathon-benchmark.suite";
+pub const ITERATION_LABEL: &str = "marathon-benchmark.iteration";
+// The process which started the container, a pid only identifies it within one boot
+pub const OWNER_PID_LABEL: &str = "marathon-benchmark.owner-pid";
+pub const OWNER_BOOT_LABEL: &str = "marathon-benchmark.owner-boot";
+
+const STOP_TIMEOUT_SECS: u32 = 10;
+const LOG_TAIL_LINES: u32 = 50;
+
+// Docker compatible engine the containers run on
+pub struct ContainerEngine {
+    client: DockerClient,
+    privileged: bool,
+    group_add: Vec<String>,
+}
+
+impl ContainerEngine {
+    pub fn docker() -> Self {
+        ContainerEngine {
+            client: DockerClient::from_env(),
+            privileged: true,
+            group_add: vec![],
+        }
+    }
+
+    // Without --privileged, /dev/kvm is passed through as a device. Rootless containers only
+    // get to open it when they keep the user's kvm group
+    pub fn podman(rootless: bool) -> Self {
+        ContainerEngine {
+            client: DockerClient::podman_from_env(rootless),
+            privileged: false,
+            group_add: if rootless {
+                vec!["keep-groups".to_owned()]
+            } else {
+                vec![]
+            },
+        }
+    }
+}
 
 pub struct DockerProvider {
+    client: DockerClient,
+    privileged: bool,
+    group_add: Vec<String>,
+    adb: AdbClient,
     image: String,
     tag: String,
     number_of_emulators: u32,
+    // How many containers are created or connected at the same time
+    concurrency: usize,
+    resources: ContainerResources,
+    // When set adb reaches the containers through a shaping proxy
+    network: Option<NetworkProfile>,
+    faults: Option<FaultInjection>,
+    fault_log: FaultLog,
     running_containers: HashMap<u32, RunningContainer>,
 }
 
 struct RunningContainer {
     id: String,
     port: u16,
+    proxy: Option<LinkProxy>,
+    timing: DeviceTiming,
 }
 
 impl RunningContainer {
-    fn new(id: String, port: u16) -> Self {
-        RunningContainer { id, port }
+    fn new(id: String, port: u16, proxy: Option<LinkProxy>, container_start: Duration) -> Self {
+        let adb_port = proxy.as_ref().map_or(port, |proxy| proxy.port());
+        let mut timing = DeviceTiming::new(serial(adb_port));
+        timing.container_start = Some(container_start);
+        RunningContainer {
+            id,
+            port,
+            proxy,
+            timing,
+        }
+    }
+
+    // Port adb connects to, the proxy's if the link is shaped
+    fn adb_port(&self) -> u16 {
+        self.proxy.as_ref().map_or(self.port, |proxy| proxy.port())
     }
 }
 
 impl DockerProvider {
-    pub fn new(image: String, tag: String, number_of_emulators: u32) -> Self {
+    pub fn new(
+        engine: ContainerEngine,
+        image: String,
+        tag: String,
+        number_of_emulators: u32,
+        concurrency: usize,
+        resources: ContainerResources,
+        network: Option<NetworkProfile>,
+    ) -> Self {
         DockerProvider {
+            client: engine.client,
+            privileged: engine.privileged,
+            group_add: engine.group_add,
+            adb: AdbClient::from_env(),
             image,
             tag,
             number_of_emulators,
+            concurrency: concurrency.max(1),
+            resources,
+            network,
+            faults: None,
+            fault_log: FaultLog::default(),
             running_containers: HashMap::new(),
         }
     }
 
-    async fn start_container(&mut self, idx: u32) -> Result<(), Box<dyn std::error::Error>> {
-        let port = request_open_port().expect("Cannot get next open port");
-        let home_dir = home::home_dir().expect("Home dir is not available for this user");
-        let adbkey_path = home_dir.join(".android").join("adbkey");
-        let adbkey = std::fs::read_to_string(adbkey_path).expect("cannot read ~/.android/adbkey");
-
-        let image = format!("{}:{}", self.image, self.tag);
-        let adb_key_env = format!("ADBKEY=\"{}\"", adbkey);
-
-        let output = Command::new("docker")
-            .args(vec!["run", "--rm", "-d", "--privileged"])
-            .args(vec!["-e".to_string(), adb_key_env])
-            .args(vec!["--device", "/dev/kvm"])
-            .args(vec!["--publish".to_string(), format!("{}:5555", port)])
-            .arg(image)
-            .stdout(Stdio::piped())
-            .spawn()?
-            .wait_with_output()
-            .await?;
-        let stdout = std::str::from_utf8(&output.stdout).expect("Cannot get container id");
-        self.running_containers
-            .insert(idx, RunningContainer::new(stdout.to_string(), port));
-        Ok(())
+    pub fn with_faults(mut self, faults: Option<FaultInjection>) -> Self {
+        self.faults = faults;
+        self
     }
 
-    async fn adb_connect(&self, port: u16) -> Result<(), Box<dyn std::error::Error>> {
-        let address = format!("localhost:{}", port);
-        let output = Command::new("adb")
-            .arg("connect")
-            .arg(&address)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?
-            .wait_with_output()
-            .await?;
-        let stdout = std::str::from_utf8(&output.stdout).expect("Cannot get adb connect response");
+    // Containers are reached through a proxy when their link is shaped or faulty
+    async fn link_proxy(&self, idx: u32, port: u16) -> std::io::Result<Option<LinkProxy>> {
+        if self.network.is_none() && self.faults.is_none() {
+            return Ok(None);
+        }
+        let faults = self.faults.clone().map(|injection| DeviceFaults {
+            device: idx,
+            injection,
+            log: self.fault_log.clone(),
+        });
+        let proxy = LinkProxy::start(
+            ([127, 0, 0, 1], port).into(),
+            self.network.clone().unwrap_or_default(),
+            faults,
+        )
+        .await?;
+        Ok(Some(proxy))
+    }
 
-        let expected = format!("connected to {}", address);
-        if stdout.contains(&expected) {
-            Ok(())
-        } else {
-            Err(Box::new(Error::new(
-                ErrorKind::ConnectionRefused,
-                "Cannot connect to device",
-            )))
+    fn container_spec(
+        &self,
+        port: u16,
+        adbkey: &str,
+        resources: Resources,
+        context: &DeviceContext,
+    ) -> ContainerSpec {
+        let mut labels = HashMap::new();
+        labels.insert(RUN_LABEL.to_owned(), context.run_uuid.to_string());
+        labels.insert(SUITE_LABEL.to_owned(), context.suite.clone());
+        labels.insert(ITERATION_LABEL.to_owned(), context.iteration.to_string());
+        labels.insert(OWNER_PID_LABEL.to_owned(), std::process::id().to_string());
+        if let Some(boot_id) = boot_id() {
+            labels.insert(OWNER_BOOT_LABEL.to_owned(), boot_id);
+        }
+        ContainerSpec {
+            image: format!("{}:{}", self.image, self.tag),
+            env: vec![format!("ADBKEY=\"{}\"", adbkey)],
+            labels,
+            privileged: self.privileged,
+            devices: vec!["/dev/kvm".to_owned()],
+            published_ports: vec![(5555, port)],
+            auto_remove: true,
+            group_add: self.group_add.clone(),
+            resources,
         }
     }
 
-    async fn stop_containers(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let ids: Vec<String> = self
+    // Each stop waits up to the timeout, stopping them one by one would add those up
+    async fn stop_containers(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let tasks: Vec<_> = self
             .running_containers
-            .values()
-            .map(|c| &c.id)
-            .map(|id| short_docker_id(id))
+            .iter()
+            .map(|(idx, container)| {
+                let task = tokio::spawn(stop_container(self.client.clone(), container.id.clone()));
+                (*idx, task)
+            })
             .collect();
-        Command::new("docker")
-            .arg("stop")
-            .args(ids)
-            .stdout(Stdio::piped())
-            .spawn()?
-            .wait()
-            .await?;
+        // Containers which did not stop stay known so dropping the provider still kills them
+        let mut first_error = None;
+        for (idx, task) in tasks {
+            let stopped = match task.await {
+                Ok(stopped) => stopped,
+                Err(e) => Err(DockerError::Io(Error::other(e.to_string()))),
+            };
+            match stopped {
+                Ok(()) => {
+                    self.running_containers.remove(&idx);
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) => Err(Box::new(e)),
+            None => Ok(()),
+        }
+    }
+
+    fn kill_containers(&mut self) {
+        // Containers are auto removed, so killing them removes them as well
+        for container in self.running_containers.values() {
+            let _ = self.client.kill_container_blocking(&container.id);
+        }
+        self.running_containers.clear();
+    }
+}
+
+// Containers outlive the process, don't leave them behind on an error or a panic
+impl Drop for DockerProvider {
+    fn drop(&mut self) {
+        self.kill_containers();
+    }
+}
+
+fn serial(port: u16) -> String {
+    format!("localhost:{}", port)
+}
+
+// Ports are only taken once the containers are up, so make sure no two of them get the same one
+fn open_ports(count: u32) -> Vec<u16> {
+    let mut ports = HashSet::new();
+    while ports.len() < count as usize {
+        ports.insert(request_open_port().expect("Cannot get next open port"));
+    }
+    ports.into_iter().collect()
+}
+
+async fn start_container(
+    client: DockerClient,
+    spec: ContainerSpec,
+) -> Result<(String, Duration), DockerError> {
+    let started_at = Instant::now();
+    let id = client.create_container(&spec).await?;
+    if let Err(e) = client.start_container(&id).await {
+        let failure = container_failure(&client, &id, e.to_string()).await;
+        let _ = client.remove_container(&id).await;
+        return Err(failure);
+    }
+    Ok((id, started_at.elapsed()))
+}
+
+// Collects what the container printed so a failed boot can be diagnosed
+async fn container_failure(client: &DockerClient, id: &str, reason: String) -> DockerError {
+    let reason = match client.inspect_container(id).await {
+        Ok(state) if !state.running => format!(
+            "{} (container is {}, exit code {})",
+            reason, state.status, state.exit_code
+        ),
+        _ => reason,
+    };
+    let logs = client
+        .container_logs(id, LOG_TAIL_LINES)
+        .await
+        .unwrap_or_else(|e| format!("Logs are not available: {}", e));
+    DockerError::ContainerFailed {
+        id: id.to_owned(),
+        reason,
+        logs,
+    }
+}
+
+async fn adb_connect(adb: &AdbClient, port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    let address = serial(port);
+    let response = adb.connect(&address).await?;
+    let expected = format!("connected to {}", address);
+    if response.contains(&expected) {
         Ok(())
+    } else {
+        Err(Box::new(Error::new(
+            ErrorKind::ConnectionRefused,
+            format!("Cannot connect to device: {}", response),
+        )))
     }
 }
 
-fn short_docker_id(full_id: &str) -> String {
-    let (id, _) = full_id.split_at(12);
-    id.to_string()
+// Returns how long it took until adb accepted the device
+async fn connect_device(adb: AdbClient, port: u16) -> Result<Duration, String> {
+    let started_at = Instant::now();
+    tryhard::retry_fn(|| adb_connect(&adb, port))
+        .retries(10)
+        .fixed_backoff(Duration::from_millis(1000))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(started_at.elapsed())
+}
+
+async fn stop_container(client: DockerClient, id: String) -> Result<(), DockerError> {
+    match client.stop_container(&id, STOP_TIMEOUT_SECS).await {
+        Err(e) if !e.is_not_found() => return Err(e),
+        _ => {}
+    }
+    // Containers are auto removed, wait for it so the next iteration starts clean
+    match client.wait_container(&id, "removed").await {
+        Err(e) if !e.is_not_found() => Err(e),
+        _ => Ok(()),
+    }
 }
 
 #[async_trait]
 impl Provider for DockerProvider {
-    async fn prepare(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        for idx in 0..self.number_of_emulators {
-            self.start_container(idx).await?;
+    async fn prepare(&mut self, context: &DeviceContext) -> Result<(), Box<dyn std::error::Error>> {
+        let home_dir = home::home_dir().expect("Home dir is not available for this user");
+        let adbkey_path = home_dir.join(".android").join("adbkey");
+        let adbkey = std::fs::read_to_string(adbkey_path).expect("cannot read ~/.android/adbkey");
+
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+        let resources = container_resources(&self.resources, self.number_of_emulators)?;
+        let tasks: Vec<_> = (0..self.number_of_emulators)
+            .zip(open_ports(self.number_of_emulators))
+            .zip(resources)
+            .map(|((idx, port), resources)| {
+                let client = self.client.clone();
+                let spec = self.container_spec(port, &adbkey, resources, context);
+                let semaphore = semaphore.clone();
+                let task = tokio::spawn(async move {
+                    let _permit = semaphore.acquire().await;
+                    start_container(client, spec).await
+                });
+                (idx, port, task)
+            })
+            .collect();
+
+        // Keep every container that came up so terminate removes them even if another failed
+        let mut first_error = None;
+        for (idx, port, task) in tasks {
+            let started = match task.await {
+                Ok(started) => started,
+                Err(e) => Err(DockerError::Io(Error::other(e.to_string()))),
+            };
+            match started {
+                Ok((id, took)) => {
+                    let proxy = match self.link_proxy(idx, port).await {
+                        Ok(proxy) => proxy,
+                        Err(e) => {
+                            first_error.get_or_insert(DockerError::Io(e));
+                            None
+                        }
+                    };
+                    self.running_containers
+                        .insert(idx, RunningContainer::new(id, port, proxy, took));
+                }
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) => Err(Box::new(e)),
+            None => Ok(()),
         }
-        Ok(())
     }
 
-    async fn connect(&self) -> Result<(), Box<dyn std::error::Error>> {
-        for container in self.running_containers.values() {
-            tryhard::retry_fn(|| self.adb_connect(container.port))
-                .retries(10)
-                .fixed_backoff(Duration::from_millis(1000))
-                .await?;
+    async fn connect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+        let tasks: Vec<_> = self
+            .running_containers
+            .iter()
+            .map(|(idx, container)| {
+                let port = container.adb_port();
+                let adb = self.adb.clone();
+                let semaphore = semaphore.clone();
+                let task = tokio::spawn(async move {
+                    let _permit = semaphore.acquire().await;
+                    connect_device(adb, port).await
+                });
+                (*idx, task)
+            })
+            .collect();
+
+        let mut failures = vec![];
+        for (idx, task) in tasks {
+            let connected = task.await?;
+            let container = self
+                .running_containers
+                .get_mut(&idx)
+                .expect("Connected container is not running");
+            match connected {
+                Ok(adb_connect) => container.timing.adb_connect = Some(adb_connect),
+                Err(reason) => failures.push((container.id.clone(), reason)),
+            }
+        }
+        match failures.into_iter().next() {
+            Some((id, reason)) => Err(Box::new(container_failure(&self.client, &id, reason).await)),
+            None => Ok(()),
         }
-        Ok(())
     }
 
     async fn terminate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         self.stop_containers().await?;
         Ok(())
     }
+
+    fn kill(&mut self) {
+        self.kill_containers();
+    }
+
+    fn serials(&self) -> Option<Vec<String>> {
+        let mut containers: Vec<_> = self.running_containers.iter().collect();
+        containers.sort_by_key(|(idx, _)| **idx);
+        Some(
+            containers
+                .into_iter()
+                .map(|(_, container)| serial(container.adb_port()))
+                .collect(),
+        )
+    }
+
+    fn start_faults(&mut self) {
+        self.fault_log
+            .lock()
+            .expect("Fault log is poisoned")
+            .clear();
+        for container in self.running_containers.values_mut() {
+            if let Some(proxy) = &mut container.proxy {
+                proxy.arm();
+            }
+        }
+    }
+
+    fn stop_faults(&mut self) -> Vec<InjectedFault> {
+        for container in self.running_containers.values_mut() {
+            if let Some(proxy) = &mut container.proxy {
+                proxy.disarm();
+            }
+        }
+        let mut log = self.fault_log.lock().expect("Fault log is poisoned");
+        let mut faults: Vec<_> = log.drain(..).collect();
+        faults.sort_by_key(|fault| fault.after);
+        faults
+    }
+
+    async fn disrupt(
+        &mut self,
+        device: usize,
+        disruption: Disruption,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut containers: Vec<_> = self.running_containers.iter().collect();
+        containers.sort_by_key(|(idx, _)| **idx);
+        let id = match containers.get(device) {
+            Some((_, container)) => container.id.clone(),
+            None => {
+                return Err(Box::new(Error::new(
+                    ErrorKind::NotFound,
+                    format!("Device {} is not running", device),
+                )))
+            }
+        };
+        match disruption {
+            // No grace period, the device is supposed to die mid test
+            Disruption::Stop => self.client.stop_container(&id, 0).await?,
+            Disruption::Pause => self.client.pause_container(&id).await?,
+            Disruption::Resume => self.client.unpause_container(&id).await?,
+        }
+        Ok(())
+    }
+
+    fn device_timings(&self) -> Vec<DeviceTiming> {
+        let mut timings: Vec<_> = self.running_containers.iter().collect();
+        timings.sort_by_key(|(idx, _)| **idx);
+        timings
+            .into_iter()
+            .map(|(_, container)| container.timing.clone())
+            .collect()
+    }
+}
+
+// Changes with every boot of the host, so a pid of an earlier boot is never mistaken for a live one
+pub fn boot_id() -> Option<String> {
+    std::fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .ok()
+        .map(|id| id.trim().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adb::{FakeAdbServer, FakeDevice};
+
+    #[tokio::test]
+    async fn test_connect_device() {
+        let server =
+            FakeAdbServer::start(vec![FakeDevice::new("localhost:40001").needs_connect()]).await;
+        connect_device(server.client(), 40001)
+            .await
+            .expect("Cannot connect");
+        let devices = server
+            .client()
+            .devices()
+            .await
+            .expect("Cannot list devices");
+        assert_eq!("localhost:40001", devices[0].serial);
+    }
 }
@@ -0,0 +1,74 @@
+use std::io::{Error, ErrorKind};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+// Telnet style console of a local emulator, listening on the even port of its serial
+pub struct EmulatorConsole {
+    stream: BufReader<TcpStream>,
+}
+
+impl EmulatorConsole {
+    pub async fn connect(port: u16) -> Result<Self, Box<dyn std::error::Error>> {
+        let stream = TcpStream::connect(("127.0.0.1", port)).await?;
+        let mut console = EmulatorConsole {
+            stream: BufReader::new(stream),
+        };
+        console.read_reply().await?;
+        // Newer emulators refuse commands until the console is authenticated
+        if let Some(token) = auth_token() {
+            console.command(&format!("auth {}", token)).await?;
+        }
+        Ok(console)
+    }
+
+    // Serial of a local emulator, e.g. emulator-5554
+    pub async fn for_serial(serial: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let port = serial
+            .strip_prefix("emulator-")
+            .and_then(|port| port.parse().ok())
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{} is not a local emulator, it has no console", serial),
+                )
+            })?;
+        EmulatorConsole::connect(port).await
+    }
+
+    pub async fn command(&mut self, command: &str) -> Result<String, Box<dyn std::error::Error>> {
+        self.stream
+            .get_mut()
+            .write_all(format!("{}\n", command).as_bytes())
+            .await?;
+        self.read_reply().await
+    }
+
+    // Replies end with a line that is either OK or KO: <reason>
+    async fn read_reply(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        let mut reply = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                // The console closes the connection right away on kill
+                return Ok(reply);
+            }
+            let trimmed = line.trim_end();
+            if trimmed == "OK" {
+                return Ok(reply);
+            }
+            if let Some(reason) = trimmed.strip_prefix("KO:") {
+                return Err(Box::new(Error::other(format!(
+                    "Emulator console refused: {}",
+                    reason.trim()
+                ))));
+            }
+            reply.push_str(&line);
+        }
+    }
+}
+
+fn auth_token() -> Option<String> {
+    let path = home::home_dir()?.join(".emulator_console_auth_token");
+    let token = std::fs::read_to_string(path).ok()?;
+    Some(token.trim().to_owned()).filter(|token| !token.is_empty())
+}
@@ -0,0 +1,165 @@
+use crate::devices::docker_provider::{
+    boot_id, ITERATION_LABEL, OWNER_BOOT_LABEL, OWNER_PID_LABEL, RUN_LABEL, SUITE_LABEL,
+};
+use crate::docker::{ContainerSummary, DockerClient};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+// Benchmark container which is still running, found by its labels
+#[derive(Debug, PartialEq)]
+pub struct LeakedContainer {
+    pub engine: &'static str,
+    pub id: String,
+    pub run: String,
+    pub suite: String,
+    pub iteration: String,
+    pub status: String,
+}
+
+impl fmt::Display for LeakedContainer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} run {} suite {} iteration {}, {}",
+            self.engine,
+            short_id(&self.id),
+            self.run,
+            self.suite,
+            self.iteration,
+            self.status
+        )
+    }
+}
+
+impl LeakedContainer {
+    fn new(engine: &'static str, summary: ContainerSummary) -> Self {
+        let label = |key: &str| summary.labels.get(key).cloned().unwrap_or_default();
+        LeakedContainer {
+            engine,
+            run: label(RUN_LABEL),
+            suite: label(SUITE_LABEL),
+            iteration: label(ITERATION_LABEL),
+            id: summary.id,
+            status: summary.status,
+        }
+    }
+}
+
+// Every engine suites may have started containers on, engines sharing a socket are asked once
+fn engines() -> Vec<(&'static str, DockerClient)> {
+    let mut engines: Vec<(&'static str, DockerClient)> = vec![];
+    let candidates = [
+        ("docker", DockerClient::from_env()),
+        ("podman", DockerClient::podman_from_env(false)),
+        ("podman (rootless)", DockerClient::podman_from_env(true)),
+    ];
+    for (name, client) in candidates.iter() {
+        if !engines.iter().any(|(_, other)| other == client) {
+            engines.push((name, client.clone()));
+        }
+    }
+    engines
+}
+
+// Containers of runs which are still going on are not leaked, even though they carry the labels.
+// Engines which are not running are skipped, it is an error only if none answered
+pub async fn find_leaked_containers() -> Result<Vec<LeakedContainer>, Box<dyn Error>> {
+    let mut leaked = vec![];
+    let mut first_error = None;
+    let mut answered = false;
+    for (engine, client) in engines() {
+        match client.list_containers(RUN_LABEL).await {
+            Ok(containers) => {
+                answered = true;
+                leaked.extend(
+                    containers
+                        .into_iter()
+                        .filter(|summary| !has_live_owner(&summary.labels))
+                        .map(|summary| LeakedContainer::new(engine, summary)),
+                );
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    match first_error {
+        Some(e) if !answered => Err(Box::new(e)),
+        _ => Ok(leaked),
+    }
+}
+
+pub async fn remove_containers(containers: &[LeakedContainer]) -> Result<(), Box<dyn Error>> {
+    let engines = engines();
+    for container in containers {
+        let (_, client) = engines
+            .iter()
+            .find(|(engine, _)| *engine == container.engine)
+            .expect("Container of an unknown engine");
+        match client.remove_container(&container.id).await {
+            Err(e) if !e.is_not_found() => return Err(Box::new(e)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Containers of older versions have no owner and count as leaked
+fn has_live_owner(labels: &HashMap<String, String>) -> bool {
+    match (labels.get(OWNER_PID_LABEL), labels.get(OWNER_BOOT_LABEL)) {
+        (Some(pid), Some(boot)) => {
+            Some(boot) == boot_id().as_ref()
+                && pid
+                    .parse::<u32>()
+                    .is_ok_and(|pid| Path::new(&format!("/proc/{}", pid)).exists())
+        }
+        _ => false,
+    }
+}
+
+fn short_id(id: &str) -> &str {
+    id.get(..12).unwrap_or(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_from_container_summary() {
+        let mut labels = HashMap::new();
+        labels.insert(RUN_LABEL.to_owned(), "5f0c".to_owned());
+        labels.insert(SUITE_LABEL.to_owned(), "adam".to_owned());
+        let summary = ContainerSummary {
+            id: "0123456789abcdef".to_owned(),
+            labels,
+            status: "Up 3 hours".to_owned(),
+        };
+        let container = LeakedContainer::new("podman", summary);
+        assert_eq!("adam", container.suite);
+        assert_eq!("", container.iteration);
+        assert_eq!(
+            "podman 0123456789ab run 5f0c suite adam iteration , Up 3 hours",
+            container.to_string()
+        );
+    }
+
+    #[test]
+    fn test_containers_of_live_runs_are_not_leaked() {
+        let boot = boot_id().expect("Cannot read boot id");
+        let owner = |pid: &str, boot: &str| {
+            let mut labels = HashMap::new();
+            labels.insert(OWNER_PID_LABEL.to_owned(), pid.to_owned());
+            labels.insert(OWNER_BOOT_LABEL.to_owned(), boot.to_owned());
+            labels
+        };
+        let pid = std::process::id().to_string();
+        assert!(has_live_owner(&owner(&pid, &boot)));
+        assert!(!has_live_owner(&owner(&pid, "previous-boot")));
+        assert!(!has_live_owner(&owner(&u32::MAX.to_string(), &boot)));
+        assert!(!has_live_owner(&owner("", &boot)));
+        assert!(!has_live_owner(&HashMap::new()));
+    }
+}
@@ -1,51 +1,287 @@
-use crate::devices::provider::Provider;
+use crate::config::{EmulatorOptions, EmulatorPorts};
+use crate::devices::emulator_console::EmulatorConsole;
+use crate::devices::provider::{DeviceContext, Disruption, Provider};
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
 use tokio::process::{Child, Command};
 
+const FIRST_CONSOLE_PORT: u16 = 5554;
+// adb only finds local emulators on its own by scanning these
+const LAST_CONSOLE_PORT: u16 = 5584;
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct LocalEmulatorProvider {
     name: String,
     number_of_emulators: u32,
-    processes: HashMap<u32, Child>,
+    options: EmulatorOptions,
+    processes: HashMap<u32, RunningEmulator>,
+}
+
+struct RunningEmulator {
+    child: Child,
+    console_port: u16,
 }
 
 impl LocalEmulatorProvider {
-    pub fn new(name: String, number_of_emulators: u32) -> Self {
+    pub fn new(name: String, number_of_emulators: u32, options: EmulatorOptions) -> Self {
         LocalEmulatorProvider {
             name,
             number_of_emulators,
+            options,
             processes: Default::default(),
         }
     }
 
-    async fn start_emulator(&mut self, idx: u32) -> Result<(), Box<dyn std::error::Error>> {
-        let process = Command::new("emulator")
-            .arg("-no-window")
-            .arg("-read-only")
+    fn ports(&self, idx: u32) -> EmulatorPorts {
+        match self.options.ports.get(idx as usize) {
+            Some(ports) => *ports,
+            None => {
+                let console = FIRST_CONSOLE_PORT + idx as u16 * 2;
+                EmulatorPorts {
+                    console,
+                    adb: console + 1,
+                }
+            }
+        }
+    }
+
+    // Serials are emulator-<console>, which adb only reports for the ports it scans
+    fn check_ports(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.options.ports.is_empty()
+            && self.options.ports.len() < self.number_of_emulators as usize
+        {
+            return Err(Box::new(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} port pairs configured for {} emulators",
+                    self.options.ports.len(),
+                    self.number_of_emulators
+                ),
+            )));
+        }
+        for idx in 0..self.number_of_emulators {
+            let ports = self.ports(idx);
+            if ports.adb != ports.console + 1
+                || !(FIRST_CONSOLE_PORT..=LAST_CONSOLE_PORT).contains(&ports.console)
+            {
+                return Err(Box::new(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "adb cannot find an emulator on ports {},{}, the console port has to be \
+                         between {} and {} and the adb port the one after it",
+                        ports.console, ports.adb, FIRST_CONSOLE_PORT, LAST_CONSOLE_PORT
+                    ),
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    async fn start_emulator(
+        &mut self,
+        idx: u32,
+        log_dir: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let ports = self.ports(idx);
+        let log = File::create(log_dir.join(format!("emulator-{}.log", ports.console)))?;
+        let mut command = Command::new("emulator");
+        command.arg("-no-window").arg("-read-only").args(vec![
+            "-ports".to_string(),
+            format!("{},{}", ports.console, ports.adb),
+        ]);
+        if let Some(snapshot) = &self.options.snapshot {
+            command.args(vec!["-snapshot", snapshot]);
+        }
+        if self.options.no_snapshot_save {
+            command.arg("-no-snapshot-save");
+        }
+        if let Some(gpu) = &self.options.gpu {
+            command.args(vec!["-gpu", gpu]);
+        }
+        if let Some(memory_mb) = self.options.memory_mb {
+            command.args(vec!["-memory".to_string(), memory_mb.to_string()]);
+        }
+        if let Some(cores) = self.options.cores {
+            command.args(vec!["-cores".to_string(), cores.to_string()]);
+        }
+        let child = command
             .arg(self.name.clone())
+            .stdout(Stdio::from(log.try_clone()?))
+            .stderr(Stdio::from(log))
+            .kill_on_drop(true)
             .spawn()?;
-        self.processes.insert(idx, process);
+        self.processes.insert(
+            idx,
+            RunningEmulator {
+                child,
+                console_port: ports.console,
+            },
+        );
         Ok(())
     }
 }
 
+// Asks the emulator to quit through its console so it shuts down cleanly, SIGKILL is the fallback
+async fn stop_emulator(emulator: &mut RunningEmulator) -> Result<(), Box<dyn std::error::Error>> {
+    // Crashed or stopped by chaos mode already
+    if emulator.child.try_wait()?.is_some() {
+        return Ok(());
+    }
+    let console = EmulatorConsole::connect(emulator.console_port).await.ok();
+    let killed = match console {
+        Some(mut console) => console.command("kill").await.is_ok(),
+        None => false,
+    };
+    if killed
+        && tokio::time::timeout(SHUTDOWN_TIMEOUT, emulator.child.wait())
+            .await
+            .is_ok()
+    {
+        return Ok(());
+    }
+    emulator.child.kill().await?;
+    Ok(())
+}
+
 #[async_trait]
 impl Provider for LocalEmulatorProvider {
-    async fn prepare(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    async fn prepare(&mut self, context: &DeviceContext) -> Result<(), Box<dyn std::error::Error>> {
+        self.check_ports()?;
         for i in 0..self.number_of_emulators {
-            self.start_emulator(i).await?;
+            self.start_emulator(i, &context.log_dir).await?;
         }
         Ok(())
     }
 
-    async fn connect(&self) -> Result<(), Box<dyn std::error::Error>> {
+    async fn connect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         Ok(())
     }
 
     async fn terminate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        for (_key, child) in self.processes.iter_mut() {
-            child.kill().await?;
+        for (_key, emulator) in self.processes.iter_mut() {
+            stop_emulator(emulator).await?;
         }
+        self.processes.clear();
         Ok(())
     }
+
+    async fn disrupt(
+        &mut self,
+        device: usize,
+        disruption: Disruption,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut emulators: Vec<_> = self.processes.iter_mut().collect();
+        emulators.sort_by_key(|(idx, _)| **idx);
+        let emulator = match emulators.into_iter().nth(device) {
+            Some((_, emulator)) => emulator,
+            None => {
+                return Err(Box::new(Error::new(
+                    ErrorKind::NotFound,
+                    format!("Device {} is not running", device),
+                )))
+            }
+        };
+        match disruption {
+            Disruption::Stop => stop_emulator(emulator).await,
+            // Suspends the virtual CPUs, the emulator process and its adb port stay up
+            Disruption::Pause => {
+                let mut console = EmulatorConsole::connect(emulator.console_port).await?;
+                console.command("avd stop").await?;
+                Ok(())
+            }
+            Disruption::Resume => {
+                let mut console = EmulatorConsole::connect(emulator.console_port).await?;
+                console.command("avd start").await?;
+                Ok(())
+            }
+        }
+    }
+
+    fn kill(&mut self) {
+        for (_key, emulator) in self.processes.iter_mut() {
+            let _ = emulator.child.start_kill();
+        }
+        self.processes.clear();
+    }
+
+    fn serials(&self) -> Option<Vec<String>> {
+        let mut emulators: Vec<_> = self.processes.iter().collect();
+        emulators.sort_by_key(|(idx, _)| **idx);
+        Some(
+            emulators
+                .into_iter()
+                .map(|(_, emulator)| format!("emulator-{}", emulator.console_port))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DeviceProvider;
+    use uuid::Uuid;
+
+    fn options(config: &str) -> EmulatorOptions {
+        match serde_yaml::from_str(config).expect("Invalid config") {
+            DeviceProvider::LocalEmulator { options, .. } => options,
+            other => panic!("Unexpected provider {:?}", other),
+        }
+    }
+
+    fn context() -> DeviceContext {
+        DeviceContext {
+            run_uuid: Uuid::new_v4(),
+            suite: "suite".to_owned(),
+            iteration: 1,
+            log_dir: std::env::temp_dir(),
+        }
+    }
+
+    #[test]
+    fn test_ports_from_config() {
+        let options = options(
+            "local_emulator:\n  name: pixel\n  gpu: swiftshader_indirect\n  \
+             ports:\n    - console: 5580\n      adb: 5581\n",
+        );
+        assert_eq!(Some("swiftshader_indirect".to_owned()), options.gpu);
+        let provider = LocalEmulatorProvider::new("pixel".to_owned(), 1, options);
+        assert_eq!(
+            EmulatorPorts {
+                console: 5580,
+                adb: 5581
+            },
+            provider.ports(0)
+        );
+        assert!(provider.check_ports().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_prepare_rejects_ports() {
+        let too_few = options(
+            "local_emulator:\n  name: pixel\n  ports:\n    - console: 5580\n      adb: 5581\n",
+        );
+        let mut provider = LocalEmulatorProvider::new("pixel".to_owned(), 2, too_few);
+        let error = provider
+            .prepare(&context())
+            .await
+            .expect_err("Emulator without ports was started");
+        assert!(error.to_string().contains("1 port pairs configured for 2"));
+
+        let unscanned = options(
+            "local_emulator:\n  name: pixel\n  ports:\n    - console: 6000\n      adb: 7001\n",
+        );
+        let mut provider = LocalEmulatorProvider::new("pixel".to_owned(), 1, unscanned);
+        let error = provider
+            .prepare(&context())
+            .await
+            .expect_err("Emulator adb cannot find was started");
+        assert!(error.to_string().contains("6000,7001"));
+        assert_eq!(Some(vec![]), provider.serials());
+    }
 }
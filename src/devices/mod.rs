@@ -1,12 +1,36 @@
 mod available_only_provider;
 
+mod chaos;
+
 mod docker_provider;
 
+mod emulator_console;
+
+mod leaked_containers;
+
 mod local_emulator_provider;
 
+mod pool;
+
 mod provider;
 
+mod readiness;
+
+mod reset;
+
+mod resources;
+
+#[cfg(test)]
+mod simulated_provider;
+
 pub use available_only_provider::AvailableOnlyProvider;
-pub use docker_provider::DockerProvider;
+pub use chaos::{ChaosSchedule, Disturbance};
+pub use docker_provider::{ContainerEngine, DockerProvider};
+pub use leaked_containers::{find_leaked_containers, remove_containers};
 pub use local_emulator_provider::LocalEmulatorProvider;
-pub use provider::Provider;
+pub use pool::DevicePool;
+pub use provider::{DeviceContext, DeviceTiming, Disruption, Provider};
+pub use readiness::{DeviceReadiness, ReadinessCheck};
+pub use reset::ResetStage;
+#[cfg(test)]
+pub use simulated_provider::SimulatedProvider;
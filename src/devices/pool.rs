@@ -0,0 +1,210 @@
+use crate::adb::AdbClient;
+use crate::adb_server::restart_adb_server;
+use crate::config::PoolScope;
+use crate::devices::provider::{DeviceContext, DeviceTiming, Disruption, Provider};
+use crate::network::InjectedFault;
+
+// Keeps a provider's devices alive between iterations, and between suites for the run scope,
+// so they are reset instead of booted again
+pub struct DevicePool {
+    provider: Box<dyn Provider>,
+    scope: PoolScope,
+    adb: AdbClient,
+    // Suites which still have to run on these devices
+    suites: u32,
+    running: bool,
+    reused: bool,
+    // Devices were acquired before, which matters for devices the provider doesn't own
+    acquired: bool,
+    persisted: bool,
+    // Faults injected into links or chaos may have left devices in any state
+    faulted: bool,
+}
+
+impl DevicePool {
+    pub fn new(provider: Box<dyn Provider>, scope: PoolScope, adb: AdbClient) -> Self {
+        DevicePool {
+            provider,
+            scope,
+            adb,
+            suites: 0,
+            running: false,
+            reused: false,
+            acquired: false,
+            persisted: false,
+            faulted: false,
+        }
+    }
+
+    pub fn add_suite(&mut self) {
+        self.suites += 1;
+    }
+
+    // Brings devices up unless the ones of the previous iteration are still running
+    pub async fn acquire(
+        &mut self,
+        context: &DeviceContext,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let acquired = std::mem::replace(&mut self.acquired, true);
+        if self.running {
+            self.reused = true;
+            self.persisted = true;
+            return Ok(());
+        }
+        self.reused = false;
+        // Connected devices outlive their provider, they are the ones of the previous iteration
+        self.persisted = acquired && self.provider.serials().is_none();
+        restart_adb_server(&self.adb).await?;
+        // Half prepared devices need a terminate as well
+        self.running = true;
+        self.provider.prepare(context).await?;
+        self.provider.connect().await
+    }
+
+    // Devices of a failed or interrupted iteration are never reused
+    pub async fn release(&mut self, discard: bool) -> Result<(), Box<dyn std::error::Error>> {
+        if discard || self.faulted || self.scope == PoolScope::Iteration {
+            self.terminate().await
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn finish_suite(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.suites = self.suites.saturating_sub(1);
+        if self.scope != PoolScope::Run || self.suites == 0 {
+            self.terminate().await
+        } else {
+            Ok(())
+        }
+    }
+
+    // Devices dropped off adb, e.g. after a reboot
+    pub async fn reconnect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.provider.connect().await
+    }
+
+    pub fn kill(&mut self) {
+        self.running = false;
+        self.faulted = false;
+        self.provider.kill();
+    }
+
+    pub fn serials(&self) -> Option<Vec<String>> {
+        self.provider.serials()
+    }
+
+    pub fn start_faults(&mut self) {
+        self.provider.start_faults();
+    }
+
+    pub fn stop_faults(&mut self) -> Vec<InjectedFault> {
+        let faults = self.provider.stop_faults();
+        self.faulted |= !faults.is_empty();
+        faults
+    }
+
+    pub async fn disrupt(
+        &mut self,
+        device: usize,
+        disruption: Disruption,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.faulted = true;
+        self.provider.disrupt(device, disruption).await
+    }
+
+    pub fn is_reused(&self) -> bool {
+        self.reused
+    }
+
+    // Devices went through an earlier iteration and have to be reset
+    pub fn is_persisted(&self) -> bool {
+        self.persisted
+    }
+
+    // Boot timings only describe the iteration which actually booted the devices
+    pub fn device_timings(&self) -> Vec<DeviceTiming> {
+        if self.reused {
+            vec![]
+        } else {
+            self.provider.device_timings()
+        }
+    }
+
+    async fn terminate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.running {
+            return Ok(());
+        }
+        self.running = false;
+        self.faulted = false;
+        self.provider.terminate().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adb::{FakeAdbServer, FakeDevice};
+    use crate::devices::AvailableOnlyProvider;
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    fn context(iteration: u32) -> DeviceContext {
+        DeviceContext {
+            run_uuid: Uuid::nil(),
+            suite: "adam".to_owned(),
+            iteration,
+            log_dir: PathBuf::from("/tmp"),
+        }
+    }
+
+    fn pool(server: &FakeAdbServer) -> DevicePool {
+        let mut pool = DevicePool::new(
+            Box::new(AvailableOnlyProvider::new()),
+            PoolScope::Suite,
+            server.client(),
+        );
+        pool.add_suite();
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_reuses_devices_until_an_iteration_fails() {
+        let server = FakeAdbServer::start(vec![FakeDevice::new("emulator-5554")]).await;
+        let mut pool = pool(&server);
+
+        pool.acquire(&context(1)).await.expect("Cannot acquire");
+        assert!(!pool.is_reused());
+        assert_eq!(1, server.kills());
+        pool.release(false).await.expect("Cannot release");
+
+        pool.acquire(&context(2)).await.expect("Cannot acquire");
+        assert!(pool.is_reused());
+        assert_eq!(1, server.kills());
+        pool.release(true).await.expect("Cannot release");
+
+        pool.acquire(&context(3)).await.expect("Cannot acquire");
+        assert!(!pool.is_reused());
+        assert_eq!(2, server.kills());
+        pool.finish_suite().await.expect("Cannot finish suite");
+    }
+
+    #[tokio::test]
+    async fn test_connected_devices_persist_between_iterations() {
+        let server = FakeAdbServer::start(vec![FakeDevice::new("emulator-5554")]).await;
+        let mut pool = DevicePool::new(
+            Box::new(AvailableOnlyProvider::new()),
+            PoolScope::Iteration,
+            server.client(),
+        );
+        pool.add_suite();
+
+        pool.acquire(&context(1)).await.expect("Cannot acquire");
+        assert!(!pool.is_persisted());
+        pool.release(false).await.expect("Cannot release");
+        pool.acquire(&context(2)).await.expect("Cannot acquire");
+        assert!(pool.is_persisted());
+        assert!(!pool.is_reused());
+        pool.finish_suite().await.expect("Cannot finish suite");
+    }
+}
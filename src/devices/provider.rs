@@ -1,8 +1,69 @@
+use crate::network::InjectedFault;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use uuid::Uuid;
+
+// Identifies the iteration devices are prepared for
+pub struct DeviceContext {
+    pub run_uuid: Uuid,
+    pub suite: String,
+    pub iteration: u32,
+    // Providers put device logs here
+    pub log_dir: PathBuf,
+}
+
+// How long each provisioning step took for one device, steps a provider doesn't have stay empty
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceTiming {
+    pub serial: String,
+    pub container_start: Option<Duration>,
+    pub adb_connect: Option<Duration>,
+    pub boot_completed: Option<Duration>,
+}
+
+impl DeviceTiming {
+    pub fn new(serial: String) -> Self {
+        DeviceTiming {
+            serial,
+            container_start: None,
+            adb_connect: None,
+            boot_completed: None,
+        }
+    }
+}
+
+// What chaos mode does to a running device
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Disruption {
+    Stop,
+    Pause,
+    Resume,
+}
 
 #[async_trait]
 pub trait Provider {
-    async fn prepare(&mut self) -> Result<(), Box<dyn std::error::Error>>;
-    async fn connect(&self) -> Result<(), Box<dyn std::error::Error>>;
+    async fn prepare(&mut self, context: &DeviceContext) -> Result<(), Box<dyn std::error::Error>>;
+    async fn connect(&mut self) -> Result<(), Box<dyn std::error::Error>>;
     async fn terminate(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+    // Last resort when terminate hangs or the user insists, must not block on devices
+    fn kill(&mut self);
+    // Serials of the devices this provider brought up, None when it uses whatever is connected
+    fn serials(&self) -> Option<Vec<String>>;
+    fn device_timings(&self) -> Vec<DeviceTiming> {
+        vec![]
+    }
+    // Fault injection only covers the test run, providers without faulty links ignore it
+    fn start_faults(&mut self) {}
+    fn stop_faults(&mut self) -> Vec<InjectedFault> {
+        vec![]
+    }
+    // Chaos mode, devices are addressed by their position in serials()
+    async fn disrupt(
+        &mut self,
+        device: usize,
+        disruption: Disruption,
+    ) -> Result<(), Box<dyn std::error::Error>>;
 }
@@ -0,0 +1,284 @@
+use crate::adb::{AdbClient, AdbDevice};
+use crate::config::Readiness;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// Where a device got stuck on its way to being usable by a runner
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceStatus {
+    Missing,
+    Unreachable(String),
+    State(String),
+    Booting,
+    PackageManagerUnavailable,
+    ProbeFailed(i32),
+    Ready,
+}
+
+impl fmt::Display for DeviceStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceStatus::Missing => write!(f, "not listed by adb"),
+            DeviceStatus::Unreachable(e) => write!(f, "unreachable ({})", e),
+            DeviceStatus::State(state) => write!(f, "in state {}", state),
+            DeviceStatus::Booting => write!(f, "sys.boot_completed is not set"),
+            DeviceStatus::PackageManagerUnavailable => {
+                write!(f, "package manager is not available")
+            }
+            DeviceStatus::ProbeFailed(status) => write!(f, "probe exited with {}", status),
+            DeviceStatus::Ready => write!(f, "ready"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceReadiness {
+    pub serial: String,
+    // Since the readiness check started
+    pub ready_after: Duration,
+}
+
+pub struct ReadyDevices {
+    pub devices: Vec<DeviceReadiness>,
+    // Online but not created by the provider, the runner must leave them alone
+    pub foreign: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ReadinessError {
+    expected: u32,
+    elapsed: Duration,
+    devices: BTreeMap<String, DeviceStatus>,
+    adb_error: Option<String>,
+}
+
+impl fmt::Display for ReadinessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ready = self
+            .devices
+            .values()
+            .filter(|status| **status == DeviceStatus::Ready)
+            .count();
+        write!(
+            f,
+            "Only {} of {} devices became ready within {:?}",
+            ready, self.expected, self.elapsed
+        )?;
+        if let Some(e) = &self.adb_error {
+            write!(f, "\n  adb server: {}", e)?;
+        }
+        for (serial, status) in &self.devices {
+            write!(f, "\n  {}: {}", serial, status)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ReadinessError {}
+
+pub struct ReadinessCheck {
+    client: AdbClient,
+    timeout: Duration,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    probe: Option<String>,
+}
+
+impl ReadinessCheck {
+    pub fn new(config: &Readiness, client: AdbClient) -> Self {
+        ReadinessCheck {
+            client,
+            timeout: Duration::from_secs(config.timeout_secs),
+            initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+            max_backoff: Duration::from_millis(config.max_backoff_ms),
+            probe: config.probe.clone(),
+        }
+    }
+
+    // Polls until the devices passed every check, backing off between rounds. Providers which
+    // own their devices name them, otherwise any `expected` devices adb lists will do
+    pub async fn wait(
+        &self,
+        owned: Option<&[String]>,
+        expected: u32,
+    ) -> Result<ReadyDevices, ReadinessError> {
+        let started_at = Instant::now();
+        let expected = owned
+            .map(|serials| serials.len() as u32)
+            .unwrap_or(expected);
+        let mut backoff = self.initial_backoff;
+        let mut devices = BTreeMap::new();
+        let mut ready = BTreeMap::new();
+        let mut foreign = BTreeSet::new();
+        loop {
+            if let Some(serials) = owned {
+                for serial in serials {
+                    devices
+                        .entry(serial.clone())
+                        .or_insert(DeviceStatus::Missing);
+                }
+            }
+            let adb_error = match self.client.devices().await {
+                Ok(listed) => {
+                    for device in listed {
+                        if owned.is_some_and(|serials| !serials.contains(&device.serial)) {
+                            foreign.insert(device.serial);
+                            continue;
+                        }
+                        if ready.contains_key(&device.serial) {
+                            continue;
+                        }
+                        let status = self.check(&device).await;
+                        if status == DeviceStatus::Ready {
+                            ready.insert(device.serial.clone(), started_at.elapsed());
+                        }
+                        devices.insert(device.serial, status);
+                    }
+                    None
+                }
+                Err(e) => Some(e.to_string()),
+            };
+            if ready.len() >= expected as usize {
+                return Ok(ReadyDevices {
+                    devices: ready
+                        .into_iter()
+                        .map(|(serial, ready_after)| DeviceReadiness {
+                            serial,
+                            ready_after,
+                        })
+                        .collect(),
+                    foreign: foreign.into_iter().collect(),
+                });
+            }
+            if started_at.elapsed() >= self.timeout {
+                return Err(ReadinessError {
+                    expected,
+                    elapsed: started_at.elapsed(),
+                    devices,
+                    adb_error,
+                });
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(self.max_backoff);
+        }
+    }
+
+    async fn check(&self, device: &AdbDevice) -> DeviceStatus {
+        if !device.is_online() {
+            return DeviceStatus::State(device.state.clone());
+        }
+        let serial = &device.serial;
+        match self
+            .client
+            .shell(serial, "getprop sys.boot_completed")
+            .await
+        {
+            Ok(output) if output.trim() == "1" => {}
+            Ok(_) => return DeviceStatus::Booting,
+            Err(e) => return DeviceStatus::Unreachable(e.to_string()),
+        }
+        match self.client.shell(serial, "pm path android").await {
+            Ok(output) if output.trim().starts_with("package:") => {}
+            Ok(_) => return DeviceStatus::PackageManagerUnavailable,
+            Err(e) => return DeviceStatus::Unreachable(e.to_string()),
+        }
+        if let Some(probe) = &self.probe {
+            match self.client.shell_status(serial, probe).await {
+                Ok(0) => {}
+                Ok(status) => return DeviceStatus::ProbeFailed(status),
+                Err(e) => return DeviceStatus::Unreachable(e.to_string()),
+            }
+        }
+        DeviceStatus::Ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adb::{FakeAdbServer, FakeDevice};
+
+    fn readiness(timeout_secs: u64, probe: Option<&str>) -> Readiness {
+        Readiness {
+            timeout_secs,
+            initial_backoff_ms: 10,
+            max_backoff_ms: 50,
+            probe: probe.map(str::to_owned),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_owned_devices_to_boot() {
+        let server = FakeAdbServer::start(vec![
+            FakeDevice::new("emulator-5554")
+                .online_after(Duration::from_millis(100))
+                .booted_after(Duration::from_millis(300)),
+            FakeDevice::new("emulator-5556"),
+        ])
+        .await;
+        let check =
+            ReadinessCheck::new(&readiness(5, Some("pidof system_server")), server.client());
+
+        let owned = vec!["emulator-5554".to_owned()];
+        let ready = check
+            .wait(Some(&owned), 1)
+            .await
+            .expect("Device is not ready");
+        assert_eq!(1, ready.devices.len());
+        assert!(ready.devices[0].ready_after >= Duration::from_millis(300));
+        assert_eq!(vec!["emulator-5556"], ready.foreign);
+        // Foreign devices are never touched
+        assert!(server.commands("emulator-5556").is_empty());
+        assert!(server
+            .commands("emulator-5554")
+            .contains(&"pidof system_server >/dev/null 2>&1; echo $?".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn test_times_out_with_stuck_devices() {
+        let server = FakeAdbServer::start(vec![
+            FakeDevice::new("emulator-5554"),
+            FakeDevice::new("emulator-5556").booted_after(Duration::from_secs(3600)),
+            FakeDevice::new("emulator-5558").shell("pidof system_server", "", 1),
+        ])
+        .await;
+        let check =
+            ReadinessCheck::new(&readiness(1, Some("pidof system_server")), server.client());
+
+        let error = match check.wait(None, 4).await {
+            Ok(_) => panic!("Devices are ready"),
+            Err(error) => error,
+        };
+        assert_eq!(
+            Some(&DeviceStatus::Booting),
+            error.devices.get("emulator-5556")
+        );
+        assert_eq!(
+            Some(&DeviceStatus::ProbeFailed(1)),
+            error.devices.get("emulator-5558")
+        );
+        assert!(error
+            .to_string()
+            .starts_with("Only 1 of 4 devices became ready"));
+    }
+
+    #[test]
+    fn test_readiness_error_names_stuck_devices() {
+        let mut devices = BTreeMap::new();
+        devices.insert("emulator-5554".to_owned(), DeviceStatus::Ready);
+        devices.insert("localhost:40001".to_owned(), DeviceStatus::Booting);
+        let error = ReadinessError {
+            expected: 2,
+            elapsed: Duration::from_secs(300),
+            devices,
+            adb_error: None,
+        };
+        assert_eq!(
+            "Only 1 of 2 devices became ready within 300s\n  \
+             emulator-5554: ready\n  \
+             localhost:40001: sys.boot_completed is not set",
+            error.to_string()
+        );
+    }
+}
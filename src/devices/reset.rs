@@ -0,0 +1,309 @@
+use crate::adb::AdbClient;
+use crate::config::DeviceReset;
+use crate::devices::emulator_console::EmulatorConsole;
+use std::io::{Error, ErrorKind};
+use std::time::{Duration, Instant};
+
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Puts reused devices back into the state the previous runner found them in. The pool's shell
+// commands run first, then the suite's own reset
+pub struct ResetStage {
+    commands: Vec<String>,
+    config: Option<DeviceReset>,
+    adb: AdbClient,
+}
+
+impl ResetStage {
+    pub fn new(commands: Vec<String>, config: Option<DeviceReset>, adb: AdbClient) -> Self {
+        ResetStage {
+            commands,
+            config,
+            adb,
+        }
+    }
+
+    // Returns true when devices went through a reboot or a snapshot load and have to be waited
+    // for again
+    pub async fn run(
+        &self,
+        serials: &[String],
+        packages: &[String],
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        for serial in serials {
+            self.run_commands(serial).await?;
+        }
+        let config = match &self.config {
+            Some(config) => config,
+            None => return Ok(false),
+        };
+        for serial in serials {
+            self.clean(config, serial, packages).await?;
+        }
+        if let Some(snapshot) = &config.snapshot {
+            for serial in serials {
+                EmulatorConsole::for_serial(serial)
+                    .await?
+                    .command(&format!("avd snapshot load {}", snapshot))
+                    .await?;
+            }
+            return Ok(true);
+        }
+        if config.reboot {
+            for serial in serials {
+                self.adb.reboot(serial).await?;
+            }
+            for serial in serials {
+                self.wait_for_disconnect(serial).await?;
+            }
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    pub fn needs_packages(&self) -> bool {
+        self.config
+            .as_ref()
+            .is_some_and(|config| config.uninstall || config.clear_data)
+    }
+
+    async fn run_commands(&self, serial: &str) -> Result<(), Box<dyn std::error::Error>> {
+        for command in &self.commands {
+            let status = self.adb.shell_status(serial, command).await?;
+            if status != 0 {
+                return Err(Box::new(Error::other(format!(
+                    "Reset command `{}` exited with {} on {}",
+                    command, status, serial
+                ))));
+            }
+        }
+        Ok(())
+    }
+
+    async fn clean(
+        &self,
+        config: &DeviceReset,
+        serial: &str,
+        packages: &[String],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // Both fail for packages which are not installed, which is fine
+        for package in packages {
+            if config.clear_data {
+                self.adb
+                    .shell(serial, &format!("pm clear {}", package))
+                    .await?;
+            }
+            if config.uninstall {
+                self.adb
+                    .shell(serial, &format!("pm uninstall {}", package))
+                    .await?;
+            }
+        }
+        for path in &config.sdcard_paths {
+            if !is_below_sdcard(path) {
+                return Err(Box::new(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Refusing to remove {}, only paths below /sdcard/ are", path),
+                )));
+            }
+            let status = self
+                .adb
+                .shell_status(serial, &format!("rm -rf '{}'", path.replace('\'', "'\\''")))
+                .await?;
+            if status != 0 {
+                return Err(Box::new(Error::other(format!(
+                    "Cannot remove {} on {}, rm exited with {}",
+                    path, serial, status
+                ))));
+            }
+        }
+        Ok(())
+    }
+
+    // A rebooting device stays listed for a moment, readiness would see the old boot otherwise
+    async fn wait_for_disconnect(&self, serial: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let started_at = Instant::now();
+        loop {
+            let online = self
+                .adb
+                .devices()
+                .await?
+                .iter()
+                .any(|device| device.serial == serial && device.is_online());
+            if !online {
+                return Ok(());
+            }
+            if started_at.elapsed() > DISCONNECT_TIMEOUT {
+                return Err(Box::new(Error::new(
+                    ErrorKind::TimedOut,
+                    format!("{} did not go down for a reboot", serial),
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+}
+
+// Something strictly below /sdcard/, which rules out the card itself
+fn is_below_sdcard(path: &str) -> bool {
+    match path.strip_prefix("/sdcard/") {
+        Some(rest) => {
+            let components: Vec<&str> = rest
+                .split('/')
+                .filter(|component| !component.is_empty() && *component != ".")
+                .collect();
+            !components.is_empty() && !components.contains(&"..")
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adb::{FakeAdbServer, FakeDevice};
+    use crate::config::Readiness;
+    use crate::devices::ReadinessCheck;
+
+    #[tokio::test]
+    async fn test_clears_data_and_reboots() {
+        let server = FakeAdbServer::start(vec![FakeDevice::new("emulator-5554")
+            .online_after(Duration::from_millis(100))
+            .booted_after(Duration::from_millis(200))])
+        .await;
+        let readiness = ReadinessCheck::new(
+            &Readiness {
+                timeout_secs: 5,
+                initial_backoff_ms: 10,
+                max_backoff_ms: 50,
+                probe: None,
+            },
+            server.client(),
+        );
+        let serials = vec!["emulator-5554".to_owned()];
+        readiness
+            .wait(Some(&serials), 1)
+            .await
+            .expect("Device is not ready");
+
+        let reset = ResetStage::new(
+            vec![],
+            Some(DeviceReset {
+                uninstall: false,
+                clear_data: true,
+                sdcard_paths: vec!["/sdcard/marathon".to_owned()],
+                reboot: true,
+                snapshot: None,
+            }),
+            server.client(),
+        );
+        let rebooted = reset
+            .run(&serials, &["com.example".to_owned()])
+            .await
+            .expect("Cannot reset");
+        assert!(rebooted);
+        let commands = server.commands("emulator-5554");
+        assert!(commands.contains(&"pm clear com.example".to_owned()));
+        assert!(commands.contains(&"rm -rf '/sdcard/marathon' >/dev/null 2>&1; echo $?".to_owned()));
+
+        let ready = readiness
+            .wait(Some(&serials), 1)
+            .await
+            .expect("Device did not come back");
+        assert!(ready.devices[0].ready_after >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_refuses_paths_outside_sdcard() {
+        let server = FakeAdbServer::start(vec![FakeDevice::new("emulator-5554")]).await;
+        let reset = ResetStage::new(
+            vec![],
+            Some(DeviceReset {
+                uninstall: false,
+                clear_data: false,
+                sdcard_paths: vec!["/sdcard/../data".to_owned()],
+                reboot: false,
+                snapshot: None,
+            }),
+            server.client(),
+        );
+        assert!(reset.run(&["emulator-5554".to_owned()], &[]).await.is_err());
+        assert!(server.commands("emulator-5554").is_empty());
+
+        for path in &[
+            "/sdcard/",
+            "/sdcard//",
+            "/sdcard/./",
+            "/data/local",
+            "/sdcard",
+        ] {
+            assert!(!is_below_sdcard(path), "{} is accepted", path);
+        }
+        assert!(is_below_sdcard("/sdcard/marathon/./screenshots/"));
+    }
+
+    #[tokio::test]
+    async fn test_quotes_in_paths_are_escaped() {
+        let server = FakeAdbServer::start(vec![FakeDevice::new("emulator-5554")]).await;
+        let reset = ResetStage::new(
+            vec![],
+            Some(DeviceReset {
+                uninstall: false,
+                clear_data: false,
+                sdcard_paths: vec!["/sdcard/it's".to_owned()],
+                reboot: false,
+                snapshot: None,
+            }),
+            server.client(),
+        );
+        reset
+            .run(&["emulator-5554".to_owned()], &[])
+            .await
+            .expect("Cannot reset");
+        assert_eq!(
+            vec!["rm -rf '/sdcard/it'\\''s' >/dev/null 2>&1; echo $?".to_owned()],
+            server.commands("emulator-5554")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_commands_run_before_suite_reset() {
+        let server = FakeAdbServer::start(vec![
+            FakeDevice::new("emulator-5554"),
+            FakeDevice::new("emulator-5556").shell("logcat -c", "", 1),
+        ])
+        .await;
+        let reset = ResetStage::new(
+            vec!["logcat -c".to_owned()],
+            Some(DeviceReset {
+                uninstall: true,
+                clear_data: false,
+                sdcard_paths: vec![],
+                reboot: false,
+                snapshot: None,
+            }),
+            server.client(),
+        );
+        let rebooted = reset
+            .run(&["emulator-5554".to_owned()], &["com.example".to_owned()])
+            .await
+            .expect("Cannot reset");
+        assert!(!rebooted);
+        assert_eq!(
+            vec![
+                "logcat -c >/dev/null 2>&1; echo $?",
+                "pm uninstall com.example"
+            ],
+            server.commands("emulator-5554")
+        );
+
+        let error = reset
+            .run(&["emulator-5556".to_owned()], &[])
+            .await
+            .expect_err("Reset passed");
+        assert_eq!(
+            "Reset command `logcat -c` exited with 1 on emulator-5556",
+            error.to_string()
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::config::ContainerResources;
+use crate::docker::Resources;
+use std::io::{Error, ErrorKind};
+
+// Engine limits for every container, in container order
+pub fn container_resources(
+    config: &ContainerResources,
+    containers: u32,
+) -> Result<Vec<Resources>, Box<dyn std::error::Error>> {
+    let resources = Resources {
+        nano_cpus: config.cpus.map(|cpus| (cpus * 1e9) as i64),
+        cpuset: config.cpuset.clone(),
+        memory_bytes: config.memory.as_deref().map(parse_size).transpose()?,
+        shm_size_bytes: config.shm_size.as_deref().map(parse_size).transpose()?,
+    };
+    let cpusets = match config.cpu_pinning {
+        Some(pinning) => pinned_cpusets(&allowed_cpus()?, pinning.reserved_cores, containers)?
+            .into_iter()
+            .map(Some)
+            .collect(),
+        None => vec![config.cpuset.clone(); containers as usize],
+    };
+    Ok(cpusets
+        .into_iter()
+        .map(|cpuset| Resources {
+            cpuset,
+            ..resources.clone()
+        })
+        .collect())
+}
+
+// CPUs this process may run on. Affinity masks and cgroup cpusets of CI runners and containers
+// leave gaps, and the engine rejects cpusets outside of them
+fn allowed_cpus() -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+    // SAFETY: cpu_set_t is plain data, all zeros is an empty set
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    // SAFETY: the size matches the set, which lives until the call returns
+    let result =
+        unsafe { libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) };
+    if result != 0 {
+        return Err(Box::new(Error::last_os_error()));
+    }
+    Ok((0..libc::CPU_SETSIZE as usize)
+        // SAFETY: the index is below CPU_SETSIZE
+        .filter(|cpu| unsafe { libc::CPU_ISSET(*cpu, &set) })
+        .map(|cpu| cpu as u32)
+        .collect())
+}
+
+// Gives every container its own share of the CPUs after the reserved ones
+fn pinned_cpusets(
+    cpus: &[u32],
+    reserved: u32,
+    containers: u32,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let reserved = reserved as usize;
+    let containers = containers as usize;
+    if reserved + containers > cpus.len() {
+        return Err(Box::new(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Cannot pin {} containers with {} reserved cores, only {} CPUs are available",
+                containers,
+                reserved,
+                cpus.len()
+            ),
+        )));
+    }
+    let available = &cpus[reserved..];
+    let per_container = available.len() / containers.max(1);
+    Ok(available
+        .chunks(per_container.max(1))
+        .take(containers)
+        .map(cpuset)
+        .collect())
+}
+
+// Engine syntax, e.g. 2-4,7
+fn cpuset(cpus: &[u32]) -> String {
+    let mut ranges: Vec<(u32, u32)> = vec![];
+    for &cpu in cpus {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == cpu => *last = cpu,
+            _ => ranges.push((cpu, cpu)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|(first, last)| {
+            if first == last {
+                first.to_string()
+            } else {
+                format!("{}-{}", first, last)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_size(size: &str) -> Result<i64, Box<dyn std::error::Error>> {
+    let size = size.trim().to_ascii_lowercase();
+    let size = size.strip_suffix('b').unwrap_or(&size);
+    let (number, multiplier) = match size.chars().last() {
+        Some('k') => (&size[..size.len() - 1], 1 << 10),
+        Some('m') => (&size[..size.len() - 1], 1 << 20),
+        Some('g') => (&size[..size.len() - 1], 1 << 30),
+        _ => (size, 1),
+    };
+    let number: f64 = number.trim().parse().map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid size {}, expected e.g. 512m or 4g", size),
+        )
+    })?;
+    Ok((number * multiplier as f64) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(512 << 20, parse_size("512m").unwrap());
+        assert_eq!(4 << 30, parse_size("4G").unwrap());
+        assert_eq!(1536 << 20, parse_size("1.5g").unwrap());
+        assert_eq!(1024, parse_size("1024").unwrap());
+        assert!(parse_size("lots").is_err());
+    }
+
+    #[test]
+    fn test_pinned_cpusets() {
+        let cores: Vec<u32> = (0..8).collect();
+        assert_eq!(vec!["2-4", "5-7"], pinned_cpusets(&cores, 2, 2).unwrap());
+        // Restricted to CPUs 4, 5, 7, 8, 9 and 12
+        let allowed = [4, 5, 7, 8, 9, 12];
+        assert_eq!(vec!["7-8", "9,12"], pinned_cpusets(&allowed, 2, 2).unwrap());
+        assert_eq!(vec!["7", "8", "9"], pinned_cpusets(&allowed, 2, 3).unwrap());
+        assert!(pinned_cpusets(&cores[..4], 2, 3).is_err());
+    }
+
+    #[test]
+    fn test_allowed_cpus() {
+        let cpus = allowed_cpus().expect("Cannot read affinity");
+        assert!(!cpus.is_empty());
+        assert!(cpus.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
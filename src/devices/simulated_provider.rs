@@ -0,0 +1,84 @@
+use crate::devices::provider::{DeviceContext, DeviceTiming, Disruption, Provider};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Devices which only exist on a fake adb server, booting them takes a fixed time.
+// Lifecycle calls are logged so tests can check devices never leak
+pub struct SimulatedProvider {
+    serials: Vec<String>,
+    boot: Duration,
+    running: bool,
+    lifecycle: Arc<Mutex<Vec<String>>>,
+}
+
+impl SimulatedProvider {
+    pub fn new(serials: Vec<String>, boot: Duration, lifecycle: Arc<Mutex<Vec<String>>>) -> Self {
+        SimulatedProvider {
+            serials,
+            boot,
+            running: false,
+            lifecycle,
+        }
+    }
+
+    fn log(&self, event: String) {
+        self.lifecycle
+            .lock()
+            .expect("Lifecycle log is poisoned")
+            .push(event);
+    }
+}
+
+#[async_trait]
+impl Provider for SimulatedProvider {
+    async fn prepare(&mut self, context: &DeviceContext) -> Result<(), Box<dyn std::error::Error>> {
+        tokio::time::sleep(self.boot).await;
+        self.running = true;
+        self.log(format!("prepare {} {}", context.suite, context.iteration));
+        Ok(())
+    }
+
+    async fn connect(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    async fn terminate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.running = false;
+        self.log("terminate".to_owned());
+        Ok(())
+    }
+
+    fn kill(&mut self) {
+        self.running = false;
+        self.log("kill".to_owned());
+    }
+
+    fn serials(&self) -> Option<Vec<String>> {
+        Some(if self.running {
+            self.serials.clone()
+        } else {
+            vec![]
+        })
+    }
+
+    fn device_timings(&self) -> Vec<DeviceTiming> {
+        self.serials
+            .iter()
+            .map(|serial| {
+                let mut timing = DeviceTiming::new(serial.clone());
+                timing.container_start = Some(self.boot);
+                timing
+            })
+            .collect()
+    }
+
+    async fn disrupt(
+        &mut self,
+        device: usize,
+        disruption: Disruption,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.log(format!("{:?} {}", disruption, self.serials[device]));
+        Ok(())
+    }
+}
@@ -0,0 +1,473 @@
+use crate::docker::error::DockerError;
+use crate::docker::http::{self, Response};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const API_VERSION: &str = "v1.40";
+const DEFAULT_SOCKET: &str = "/var/run/docker.sock";
+const PODMAN_SOCKET: &str = "/run/podman/podman.sock";
+
+#[derive(Clone, PartialEq)]
+pub struct DockerClient {
+    socket: PathBuf,
+}
+
+// What `docker run` flags used to express, for POST /containers/create
+pub struct ContainerSpec {
+    pub image: String,
+    pub env: Vec<String>,
+    pub labels: HashMap<String, String>,
+    pub privileged: bool,
+    pub devices: Vec<String>,
+    // container port -> host port, tcp
+    pub published_ports: Vec<(u16, u16)>,
+    pub auto_remove: bool,
+    // Supplementary groups, podman's keep-groups keeps the caller's, e.g. kvm
+    pub group_add: Vec<String>,
+    pub resources: Resources,
+}
+
+// Engine units, left out of the request when not set
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Resources {
+    pub nano_cpus: Option<i64>,
+    pub cpuset: Option<String>,
+    pub memory_bytes: Option<i64>,
+    pub shm_size_bytes: Option<i64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ContainerState {
+    pub status: String,
+    pub running: bool,
+    pub exit_code: i64,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct ContainerSummary {
+    #[serde(rename = "Id")]
+    pub id: String,
+    #[serde(rename = "Labels", default)]
+    pub labels: HashMap<String, String>,
+    #[serde(rename = "Status", default)]
+    pub status: String,
+}
+
+impl DockerClient {
+    pub fn new(socket: PathBuf) -> Self {
+        DockerClient { socket }
+    }
+
+    // Honours DOCKER_HOST when it points to a unix socket
+    pub fn from_env() -> Self {
+        let socket = std::env::var("DOCKER_HOST")
+            .ok()
+            .and_then(|host| host.strip_prefix("unix://").map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_SOCKET));
+        DockerClient::new(socket)
+    }
+
+    // Podman serves the same API, rootless it listens in the user's runtime dir
+    pub fn podman_from_env(rootless: bool) -> Self {
+        let socket = std::env::var("CONTAINER_HOST")
+            .ok()
+            .and_then(|host| host.strip_prefix("unix://").map(PathBuf::from))
+            .unwrap_or_else(|| {
+                if rootless {
+                    let runtime_dir = std::env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| {
+                        // SAFETY: getuid has no preconditions and cannot fail
+                        format!("/run/user/{}", unsafe { libc::getuid() })
+                    });
+                    PathBuf::from(runtime_dir)
+                        .join("podman")
+                        .join("podman.sock")
+                } else {
+                    PathBuf::from(PODMAN_SOCKET)
+                }
+            });
+        DockerClient::new(socket)
+    }
+
+    // Returns the container id, pulling the image first if the engine doesn't have it
+    pub async fn create_container(&self, spec: &ContainerSpec) -> Result<String, DockerError> {
+        let body = create_body(spec);
+        let response = match self.post("/containers/create", Some(&body)).await {
+            Err(e) if e.is_not_found() => {
+                self.pull_image(&spec.image).await?;
+                self.post("/containers/create", Some(&body)).await?
+            }
+            response => response?,
+        };
+        let created: Value = serde_json::from_slice(&response.body)?;
+        created["Id"]
+            .as_str()
+            .filter(|id| !id.is_empty())
+            .map(str::to_owned)
+            .ok_or_else(|| DockerError::InvalidResponse("container id is missing".to_owned()))
+    }
+
+    pub async fn pull_image(&self, image: &str) -> Result<(), DockerError> {
+        let (name, tag) = match image.rfind(':') {
+            Some(idx) if !image[idx..].contains('/') => (&image[..idx], &image[idx + 1..]),
+            _ => (image, "latest"),
+        };
+        let path = format!(
+            "/images/create?fromImage={}&tag={}",
+            percent_encode(name),
+            percent_encode(tag)
+        );
+        let response = self.post(&path, None).await?;
+        // Pull failures after the stream started are only reported inside the stream
+        for line in response.body.split(|b| *b == b'\n') {
+            if let Ok(progress) = serde_json::from_slice::<Value>(line) {
+                if let Some(message) = progress["error"].as_str() {
+                    return Err(DockerError::Api {
+                        status: response.status,
+                        message: message.to_owned(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn start_container(&self, id: &str) -> Result<(), DockerError> {
+        self.post(&format!("/containers/{}/start", id), None)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn inspect_container(&self, id: &str) -> Result<ContainerState, DockerError> {
+        let response = self
+            .request("GET", &format!("/containers/{}/json", id), None)
+            .await?;
+        let inspect: Value = serde_json::from_slice(&response.body)?;
+        let state = &inspect["State"];
+        Ok(ContainerState {
+            status: state["Status"].as_str().unwrap_or_default().to_owned(),
+            running: state["Running"].as_bool().unwrap_or_default(),
+            exit_code: state["ExitCode"].as_i64().unwrap_or_default(),
+        })
+    }
+
+    pub async fn stop_container(&self, id: &str, timeout_secs: u32) -> Result<(), DockerError> {
+        self.post(&format!("/containers/{}/stop?t={}", id, timeout_secs), None)
+            .await
+            .map(|_| ())
+    }
+
+    // Freezes every process of the container, its device stops answering without going away
+    pub async fn pause_container(&self, id: &str) -> Result<(), DockerError> {
+        self.post(&format!("/containers/{}/pause", id), None)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn unpause_container(&self, id: &str) -> Result<(), DockerError> {
+        self.post(&format!("/containers/{}/unpause", id), None)
+            .await
+            .map(|_| ())
+    }
+
+    // Blocks until the container reaches the condition, e.g. "not-running" or "removed"
+    pub async fn wait_container(&self, id: &str, condition: &str) -> Result<i64, DockerError> {
+        let path = format!("/containers/{}/wait?condition={}", id, condition);
+        let response = self.post(&path, None).await?;
+        let wait: Value = serde_json::from_slice(&response.body)?;
+        Ok(wait["StatusCode"].as_i64().unwrap_or_default())
+    }
+
+    pub async fn remove_container(&self, id: &str) -> Result<(), DockerError> {
+        self.request("DELETE", &format!("/containers/{}?force=true", id), None)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn container_logs(&self, id: &str, tail: u32) -> Result<String, DockerError> {
+        let path = format!(
+            "/containers/{}/logs?stdout=true&stderr=true&tail={}",
+            id, tail
+        );
+        let response = self.request("GET", &path, None).await?;
+        Ok(demultiplex(&response.body))
+    }
+
+    pub async fn list_containers(&self, label: &str) -> Result<Vec<ContainerSummary>, DockerError> {
+        let filters = json!({ "label": [label] }).to_string();
+        let path = format!("/containers/json?filters={}", percent_encode(&filters));
+        let response = self.request("GET", &path, None).await?;
+        Ok(serde_json::from_slice(&response.body)?)
+    }
+
+    // Used from Drop where there is no runtime to await on
+    pub fn kill_container_blocking(&self, id: &str) -> Result<(), DockerError> {
+        let path = format!("/{}/containers/{}/kill", API_VERSION, id);
+        check(http::send_blocking(&self.socket, "POST", &path, None)?).map(|_| ())
+    }
+
+    async fn post(&self, path: &str, body: Option<&Value>) -> Result<Response, DockerError> {
+        self.request("POST", path, body).await
+    }
+
+    async fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<Response, DockerError> {
+        let body = body.map(Value::to_string);
+        let path = format!("/{}{}", API_VERSION, path);
+        let response = http::send(
+            &self.socket,
+            method,
+            &path,
+            body.as_ref().map(String::as_bytes),
+        )
+        .await?;
+        check(response)
+    }
+}
+
+// 304 means the container already is in the requested state
+fn check(response: Response) -> Result<Response, DockerError> {
+    if response.status < 400 {
+        return Ok(response);
+    }
+    let message = serde_json::from_slice::<Value>(&response.body)
+        .ok()
+        .and_then(|error| error["message"].as_str().map(str::to_owned))
+        .unwrap_or_else(|| String::from_utf8_lossy(&response.body).trim().to_owned());
+    Err(DockerError::Api {
+        status: response.status,
+        message,
+    })
+}
+
+fn create_body(spec: &ContainerSpec) -> Value {
+    let exposed_ports: HashMap<String, Value> = spec
+        .published_ports
+        .iter()
+        .map(|(container, _)| (format!("{}/tcp", container), json!({})))
+        .collect();
+    let port_bindings: HashMap<String, Value> = spec
+        .published_ports
+        .iter()
+        .map(|(container, host)| {
+            (
+                format!("{}/tcp", container),
+                json!([{ "HostPort": host.to_string() }]),
+            )
+        })
+        .collect();
+    let devices: Vec<Value> = spec
+        .devices
+        .iter()
+        .map(|device| {
+            json!({
+                "PathOnHost": device,
+                "PathInContainer": device,
+                "CgroupPermissions": "rwm",
+            })
+        })
+        .collect();
+    let mut host_config = json!({
+        "Privileged": spec.privileged,
+        "Devices": devices,
+        "PortBindings": port_bindings,
+        "AutoRemove": spec.auto_remove,
+        "GroupAdd": spec.group_add,
+    });
+    let resources = &spec.resources;
+    if let Some(nano_cpus) = resources.nano_cpus {
+        host_config["NanoCpus"] = json!(nano_cpus);
+    }
+    if let Some(cpuset) = &resources.cpuset {
+        host_config["CpusetCpus"] = json!(cpuset);
+    }
+    if let Some(memory) = resources.memory_bytes {
+        host_config["Memory"] = json!(memory);
+    }
+    if let Some(shm_size) = resources.shm_size_bytes {
+        host_config["ShmSize"] = json!(shm_size);
+    }
+    json!({
+        "Image": spec.image,
+        "Env": spec.env,
+        "Labels": spec.labels,
+        "ExposedPorts": exposed_ports,
+        "HostConfig": host_config,
+    })
+}
+
+// Without a TTY the engine prefixes every frame with an 8 byte header: stream, 0, 0, 0, size
+fn demultiplex(mut raw: &[u8]) -> String {
+    let mut output = vec![];
+    while raw.len() >= 8 && raw[0] <= 2 && raw[1..4] == [0, 0, 0] {
+        let size = u32::from_be_bytes([raw[4], raw[5], raw[6], raw[7]]) as usize;
+        let end = (8 + size).min(raw.len());
+        output.extend_from_slice(&raw[8..end]);
+        raw = &raw[end..];
+    }
+    output.extend_from_slice(raw);
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::UnixListener;
+
+    // Answers one connection per canned response and hands back the request lines it saw
+    fn fake_engine(
+        responses: Vec<&'static str>,
+    ) -> (DockerClient, tokio::task::JoinHandle<Vec<String>>) {
+        let socket = std::env::temp_dir().join(format!("docker-{}.sock", uuid::Uuid::new_v4()));
+        let listener = UnixListener::bind(&socket).expect("Cannot bind fake engine");
+        let client = DockerClient::new(socket.clone());
+        let server = tokio::spawn(async move {
+            let mut requests = vec![];
+            for response in responses {
+                let (mut stream, _) = listener.accept().await.expect("Cannot accept");
+                let mut buffer = vec![0; 4096];
+                let read = stream.read(&mut buffer).await.expect("Cannot read request");
+                let request = String::from_utf8_lossy(&buffer[..read]).into_owned();
+                requests.push(request.lines().next().unwrap_or_default().to_owned());
+                stream
+                    .write_all(response.as_bytes())
+                    .await
+                    .expect("Cannot write response");
+            }
+            std::fs::remove_file(socket).expect("Cannot remove socket");
+            requests
+        });
+        (client, server)
+    }
+
+    fn spec() -> ContainerSpec {
+        ContainerSpec {
+            image: "emulator:30".to_owned(),
+            env: vec![],
+            labels: HashMap::new(),
+            privileged: true,
+            devices: vec!["/dev/kvm".to_owned()],
+            published_ports: vec![(5555, 40000)],
+            auto_remove: true,
+            group_add: vec![],
+            resources: Resources {
+                memory_bytes: Some(4 << 30),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_pulls_missing_image() {
+        let (client, server) = fake_engine(vec![
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 35\r\n\r\n{\"message\":\"No such image: em:30\"}\n",
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n15\r\n{\"status\":\"pulling\"}\n\r\n0\r\n\r\n",
+            "HTTP/1.1 201 Created\r\nContent-Length: 27\r\n\r\n{\"Id\":\"abc\",\"Warnings\":[]}",
+        ]);
+        let id = client
+            .create_container(&spec())
+            .await
+            .expect("Cannot create");
+        assert_eq!("abc", id);
+        let requests = server.await.expect("Fake engine failed");
+        assert_eq!(
+            vec![
+                "POST /v1.40/containers/create HTTP/1.1",
+                "POST /v1.40/images/create?fromImage=emulator&tag=30 HTTP/1.1",
+                "POST /v1.40/containers/create HTTP/1.1",
+            ],
+            requests
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pull_encodes_reference() {
+        let (client, server) = fake_engine(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 21\r\n\r\n{\"status\":\"pulling\"}\n",
+        ]);
+        client
+            .pull_image("registry:5000/emu&lator:30?x#1")
+            .await
+            .expect("Cannot pull");
+        let requests = server.await.expect("Fake engine failed");
+        assert_eq!(
+            vec![
+                "POST /v1.40/images/create?fromImage=registry%3A5000%2Femu%26lator&tag=30%3Fx%231 \
+                 HTTP/1.1"
+            ],
+            requests
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_error_is_structured() {
+        let (client, server) = fake_engine(vec![
+            "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 26\r\n\r\n{\"message\":\"kvm missing\"}",
+        ]);
+        match client.start_container("abc").await {
+            Err(DockerError::Api { status, message }) => {
+                assert_eq!(500, status);
+                assert_eq!("kvm missing", message);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+        server.await.expect("Fake engine failed");
+    }
+
+    #[tokio::test]
+    async fn test_inspect_and_logs() {
+        let (client, server) = fake_engine(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 54\r\n\r\n{\"State\":{\"Status\":\"exited\",\"Running\":false,\"ExitCode\":3}}",
+            "HTTP/1.1 200 OK\r\nContent-Length: 14\r\n\r\n\x01\x00\x00\x00\x00\x00\x00\x06booted",
+        ]);
+        let state = client
+            .inspect_container("abc")
+            .await
+            .expect("Cannot inspect");
+        assert_eq!(
+            ContainerState {
+                status: "exited".to_owned(),
+                running: false,
+                exit_code: 3,
+            },
+            state
+        );
+        let logs = client
+            .container_logs("abc", 10)
+            .await
+            .expect("Cannot get logs");
+        assert_eq!("booted", logs);
+        server.await.expect("Fake engine failed");
+    }
+
+    #[test]
+    fn test_create_body() {
+        let body = create_body(&spec());
+        assert_eq!(
+            json!([{ "HostPort": "40000" }]),
+            body["HostConfig"]["PortBindings"]["5555/tcp"]
+        );
+        assert_eq!("/dev/kvm", body["HostConfig"]["Devices"][0]["PathOnHost"]);
+        assert_eq!(json!({}), body["ExposedPorts"]["5555/tcp"]);
+        assert_eq!(json!(4u64 << 30), body["HostConfig"]["Memory"]);
+        assert!(body["HostConfig"].get("NanoCpus").is_none());
+    }
+}
@@ -0,0 +1,56 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum DockerError {
+    Io(io::Error),
+    // The engine answered with an error status
+    Api {
+        status: u16,
+        message: String,
+    },
+    InvalidResponse(String),
+    // The container started but did not come up, logs are the tail of its output
+    ContainerFailed {
+        id: String,
+        reason: String,
+        logs: String,
+    },
+}
+
+impl DockerError {
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, DockerError::Api { status: 404, .. })
+    }
+}
+
+impl fmt::Display for DockerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DockerError::Io(e) => write!(f, "Cannot talk to the Docker engine: {}", e),
+            DockerError::Api { status, message } => {
+                write!(f, "Docker engine returned {}: {}", status, message)
+            }
+            DockerError::InvalidResponse(message) => {
+                write!(f, "Invalid response from the Docker engine: {}", message)
+            }
+            DockerError::ContainerFailed { id, reason, logs } => {
+                write!(f, "Container {} failed: {}\n{}", id, reason, logs)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DockerError {}
+
+impl From<io::Error> for DockerError {
+    fn from(e: io::Error) -> Self {
+        DockerError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for DockerError {
+    fn from(e: serde_json::Error) -> Self {
+        DockerError::InvalidResponse(e.to_string())
+    }
+}
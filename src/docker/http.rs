@@ -0,0 +1,133 @@
+use crate::docker::error::DockerError;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+// Just enough HTTP/1.1 for the engine API, one request per connection
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+pub async fn send(
+    socket: &Path,
+    method: &str,
+    path: &str,
+    body: Option<&[u8]>,
+) -> Result<Response, DockerError> {
+    let mut stream = tokio::net::UnixStream::connect(socket).await?;
+    stream
+        .write_all(&encode_request(method, path, body))
+        .await?;
+    let mut raw = vec![];
+    stream.read_to_end(&mut raw).await?;
+    parse_response(&raw)
+}
+
+// Drop runs on shutdown paths, a hung engine must not keep the process from exiting
+const BLOCKING_TIMEOUT: Duration = Duration::from_secs(5);
+
+// For Drop implementations which cannot await
+pub fn send_blocking(
+    socket: &Path,
+    method: &str,
+    path: &str,
+    body: Option<&[u8]>,
+) -> Result<Response, DockerError> {
+    let mut stream = std::os::unix::net::UnixStream::connect(socket)?;
+    stream.set_read_timeout(Some(BLOCKING_TIMEOUT))?;
+    stream.set_write_timeout(Some(BLOCKING_TIMEOUT))?;
+    stream.write_all(&encode_request(method, path, body))?;
+    let mut raw = vec![];
+    stream.read_to_end(&mut raw)?;
+    parse_response(&raw)
+}
+
+fn encode_request(method: &str, path: &str, body: Option<&[u8]>) -> Vec<u8> {
+    let body = body.unwrap_or_default();
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: docker\r\nConnection: close\r\nContent-Length: {}\r\n",
+        method,
+        path,
+        body.len()
+    );
+    if !body.is_empty() {
+        request.push_str("Content-Type: application/json\r\n");
+    }
+    request.push_str("\r\n");
+    let mut request = request.into_bytes();
+    request.extend_from_slice(body);
+    request
+}
+
+fn parse_response(raw: &[u8]) -> Result<Response, DockerError> {
+    let invalid = |message: &str| DockerError::InvalidResponse(message.to_owned());
+    let header_end = find(raw, b"\r\n\r\n").ok_or_else(|| invalid("missing headers"))?;
+    let head = std::str::from_utf8(&raw[..header_end]).map_err(|_| invalid("headers"))?;
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| invalid("missing status line"))?;
+    let chunked = lines.any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("transfer-encoding:") && line.contains("chunked")
+    });
+    let body = &raw[header_end + 4..];
+    let body = if chunked {
+        dechunk(body)?
+    } else {
+        body.to_vec()
+    };
+    Ok(Response { status, body })
+}
+
+fn dechunk(mut body: &[u8]) -> Result<Vec<u8>, DockerError> {
+    let invalid = || DockerError::InvalidResponse("malformed chunked body".to_owned());
+    let mut decoded = vec![];
+    loop {
+        let line_end = find(body, b"\r\n").ok_or_else(invalid)?;
+        let size_line = std::str::from_utf8(&body[..line_end]).map_err(|_| invalid())?;
+        let size_hex = size_line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size_hex, 16).map_err(|_| invalid())?;
+        if size == 0 {
+            return Ok(decoded);
+        }
+        let start = line_end + 2;
+        let chunk = body.get(start..start + size).ok_or_else(invalid)?;
+        decoded.extend_from_slice(chunk);
+        body = body.get(start + size + 2..).ok_or_else(invalid)?;
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chunked_response() {
+        let raw = b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                    Transfer-Encoding: chunked\r\n\r\n5\r\n{\"a\":\r\n3\r\n 1}\r\n0\r\n\r\n";
+        let response = parse_response(raw).expect("Cannot parse response");
+        assert_eq!(200, response.status);
+        assert_eq!(b"{\"a\": 1}".to_vec(), response.body);
+    }
+
+    #[test]
+    fn test_encode_request() {
+        let request = encode_request("POST", "/v1.40/containers/create", Some(b"{}"));
+        let expected = "POST /v1.40/containers/create HTTP/1.1\r\nHost: docker\r\n\
+                        Connection: close\r\nContent-Length: 2\r\n\
+                        Content-Type: application/json\r\n\r\n{}";
+        assert_eq!(expected.as_bytes(), request.as_slice());
+    }
+}
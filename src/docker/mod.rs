@@ -0,0 +1,8 @@
+mod client;
+
+mod error;
+
+mod http;
+
+pub use client::{ContainerSpec, ContainerSummary, DockerClient, Resources};
+pub use error::DockerError;
@@ -1,4 +1,5 @@
 use crate::benchmark_results::ExecutionReport;
+use crate::monitoring::{ProcessMonitoringReport, SystemMonitoringReport};
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
@@ -30,6 +31,29 @@ impl FileManager {
         Ok(())
     }
 
+    pub async fn save_system_monitoring_report(
+        &self,
+        report: SystemMonitoringReport,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let report = serde_json::to_string(&report)?;
+        let working_dir = self.get_working_dir()?;
+        let path = working_dir.join("system_monitoring.json");
+        std::fs::write(path, report)?;
+        Ok(())
+    }
+
+    pub async fn save_process_monitoring_report(
+        &self,
+        report: ProcessMonitoringReport,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let report = serde_json::to_string(&report)?;
+        let results_dir = self.get_results_dir_for_iteration()?;
+        std::fs::create_dir_all(&results_dir)?;
+        let path = results_dir.join("process_monitoring.json");
+        std::fs::write(path, report)?;
+        Ok(())
+    }
+
     pub fn prepare_working_dir(
         &self,
         apk: &str,
@@ -49,15 +73,20 @@ impl FileManager {
             let file_name = Path::new(&f).file_name().expect("Expected file name");
             let dest = working_dir.join(file_name);
             std::fs::copy(&f, &dest)
-                .expect(format!("Cannot copy file: {} to {:?}", f, &dest).as_str());
+                .unwrap_or_else(|_| panic!("Cannot copy file: {} to {:?}", f, &dest));
         }
         Ok(())
     }
 
+    // Also called after a failed iteration, so the APKs may not have been copied yet
     pub fn clean_up_working_dir(&self) -> Result<(), Box<dyn std::error::Error>> {
         let working_dir = self.get_working_dir()?;
-        std::fs::remove_file(working_dir.join("application.apk"))?;
-        std::fs::remove_file(working_dir.join("test_application.apk"))?;
+        for apk in &["application.apk", "test_application.apk"] {
+            match std::fs::remove_file(working_dir.join(apk)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
         Ok(())
     }
 
@@ -72,6 +101,12 @@ impl FileManager {
         Ok(self.get_results_dir_for_iteration()?.join(tool_name))
     }
 
+    pub fn get_devices_log_dir(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let dir = self.get_results_dir().join("devices");
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
     pub fn get_results_dir_for_iteration(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
         let dir = self.get_results_dir();
         Ok(dir)
@@ -90,6 +125,7 @@ impl FileManager {
     }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
 
@@ -106,7 +142,7 @@ mod tests {
         let expected = &format!(
             "{}/{}/{}/{}",
             working_dir.to_str().expect("Cannot convert path to string"),
-            uuid.to_string(),
+            uuid,
             suite_name,
             iteration
         );
@@ -126,7 +162,7 @@ mod tests {
         let expected = &format!(
             "{}/{}/{}/{}/{}",
             working_dir.to_str().expect("Cannot convert path to string"),
-            uuid.to_string(),
+            uuid,
             suite_name,
             iteration,
             "results"
@@ -149,7 +185,7 @@ mod tests {
         let expected = &format!(
             "{}/{}/{}/{}/{}",
             working_dir.to_str().expect("Cannot convert path to string"),
-            uuid.to_string(),
+            uuid,
             suite_name,
             iteration,
             "results"
@@ -0,0 +1,377 @@
+use crate::monitoring::{ProcessMonitoringReport, SystemMonitoringReport};
+use crate::run_config::RunConfig;
+use crate::run_directory::RunDirectory;
+use crate::statistics::Statistics;
+use rusqlite::{params, Connection, Transaction};
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use sysinfo::{System, SystemExt};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    uuid TEXT PRIMARY KEY,
+    started_at INTEGER NOT NULL,
+    working_dir TEXT NOT NULL,
+    host TEXT
+);
+CREATE TABLE IF NOT EXISTS iterations (
+    run_uuid TEXT NOT NULL REFERENCES runs(uuid),
+    suite TEXT NOT NULL,
+    iteration INTEGER NOT NULL,
+    duration_secs REAL NOT NULL,
+    host_average_cpu REAL,
+    host_peak_memory_kb INTEGER,
+    process_cpu_time_secs REAL,
+    process_average_cpu REAL,
+    process_peak_rss_kb INTEGER,
+    PRIMARY KEY (run_uuid, suite, iteration)
+);
+";
+
+// Applied in order to databases whose user_version is lower than their position plus one
+const MIGRATIONS: &[&str] = &["ALTER TABLE iterations ADD COLUMN valid INTEGER NOT NULL DEFAULT 1"];
+
+// Benchmark results of every run kept in a local SQLite file
+pub struct HistoryStore {
+    connection: Connection,
+}
+
+struct RunRecord {
+    uuid: String,
+    started_at: i64,
+    working_dir: String,
+    host: Option<String>,
+}
+
+struct IterationRecord<'a> {
+    run_uuid: &'a str,
+    suite: &'a str,
+    iteration: u32,
+    duration_secs: f64,
+    valid: bool,
+    system: Option<SystemMonitoringReport>,
+    process: Option<ProcessMonitoringReport>,
+}
+
+struct RunDurations {
+    run_uuid: String,
+    started_at: String,
+    durations: Vec<f64>,
+}
+
+pub struct SuiteTrend {
+    pub suite: String,
+    pub runs: Vec<TrendPoint>,
+}
+
+pub struct TrendPoint {
+    pub run_uuid: String,
+    pub started_at: String,
+    pub duration: Statistics,
+}
+
+impl HistoryStore {
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, Box<dyn Error>> {
+        connection.execute_batch(SCHEMA)?;
+        migrate(&connection)?;
+        Ok(HistoryStore { connection })
+    }
+
+    pub fn ingest(
+        &mut self,
+        run_config: &RunConfig,
+        run_directory: &RunDirectory,
+    ) -> Result<(), Box<dyn Error>> {
+        let run = RunRecord {
+            uuid: run_config.uuid.to_string(),
+            started_at: run_config.started_at.duration_since(UNIX_EPOCH)?.as_secs() as i64,
+            working_dir: run_config.working_dir.to_string_lossy().into_owned(),
+            host: System::new().get_host_name(),
+        };
+        let reports = run_directory.load_execution_reports()?;
+        let transaction = self.connection.transaction()?;
+        insert_run(&transaction, &run)?;
+        for report in &reports {
+            let suite = report.suite_name();
+            let iteration = report.iteration();
+            insert_iteration(
+                &transaction,
+                IterationRecord {
+                    run_uuid: &run.uuid,
+                    suite,
+                    iteration,
+                    duration_secs: report.duration().as_secs_f64(),
+                    valid: report.is_valid(),
+                    system: run_directory.load_system_monitoring_report(suite, iteration)?,
+                    process: run_directory.load_process_monitoring_report(suite, iteration)?,
+                },
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    // Runs in chronological order, only the last `limit` runs of every suite are returned
+    pub fn trends(
+        &self,
+        suite: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<SuiteTrend>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare(
+            "SELECT i.suite, r.uuid, datetime(r.started_at, 'unixepoch'), i.duration_secs
+             FROM iterations i JOIN runs r ON r.uuid = i.run_uuid
+             WHERE i.valid AND (?1 IS NULL OR i.suite = ?1)
+             ORDER BY i.suite, r.started_at, r.uuid",
+        )?;
+        let rows = statement.query_map(params![suite], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, f64>(3)?,
+            ))
+        })?;
+
+        let mut grouped: Vec<(String, Vec<RunDurations>)> = vec![];
+        for row in rows {
+            let (suite, run_uuid, started_at, duration) = row?;
+            if grouped.last().map(|(s, _)| s != &suite).unwrap_or(true) {
+                grouped.push((suite, vec![]));
+            }
+            let runs = &mut grouped.last_mut().expect("Expected suite").1;
+            if runs.last().map(|r| r.run_uuid != run_uuid).unwrap_or(true) {
+                runs.push(RunDurations {
+                    run_uuid,
+                    started_at,
+                    durations: vec![],
+                });
+            }
+            runs.last_mut()
+                .expect("Expected run")
+                .durations
+                .push(duration);
+        }
+
+        Ok(grouped
+            .into_iter()
+            .map(|(suite, runs)| {
+                let skip = runs.len().saturating_sub(limit);
+                SuiteTrend {
+                    suite,
+                    runs: runs
+                        .into_iter()
+                        .skip(skip)
+                        .filter_map(|run| {
+                            Some(TrendPoint {
+                                duration: Statistics::new(&run.durations)?,
+                                run_uuid: run.run_uuid,
+                                started_at: run.started_at,
+                            })
+                        })
+                        .collect(),
+                }
+            })
+            .collect())
+    }
+}
+
+fn insert_run(transaction: &Transaction, run: &RunRecord) -> Result<(), Box<dyn Error>> {
+    transaction.execute(
+        "INSERT OR REPLACE INTO runs (uuid, started_at, working_dir, host) VALUES (?1, ?2, ?3, ?4)",
+        params![run.uuid, run.started_at, run.working_dir, run.host],
+    )?;
+    Ok(())
+}
+
+fn migrate(connection: &Connection) -> Result<(), Box<dyn Error>> {
+    let version: i64 = connection.query_row("PRAGMA user_version", params![], |row| row.get(0))?;
+    for (idx, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        connection.execute_batch(&format!(
+            "BEGIN; {}; PRAGMA user_version = {}; COMMIT;",
+            migration,
+            idx + 1
+        ))?;
+    }
+    Ok(())
+}
+
+fn insert_iteration(
+    transaction: &Transaction,
+    record: IterationRecord,
+) -> Result<(), Box<dyn Error>> {
+    let host_average_cpu = record.system.as_ref().and_then(|report| {
+        let samples = &report.samples;
+        if samples.is_empty() {
+            return None;
+        }
+        let total: f64 = samples
+            .iter()
+            .map(|s| {
+                s.cpu_per_core.iter().map(|c| f64::from(*c)).sum::<f64>()
+                    / s.cpu_per_core.len().max(1) as f64
+            })
+            .sum();
+        Some(total / samples.len() as f64)
+    });
+    let host_peak_memory_kb = record
+        .system
+        .as_ref()
+        .and_then(|report| report.samples.iter().map(|s| s.memory_used_kb).max())
+        .map(|kb| kb as i64);
+    let process = record.process.as_ref().map(|report| &report.summary);
+    transaction.execute(
+        "INSERT OR REPLACE INTO iterations (
+            run_uuid, suite, iteration, duration_secs, valid, host_average_cpu,
+            host_peak_memory_kb, process_cpu_time_secs, process_average_cpu, process_peak_rss_kb
+         ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            record.run_uuid,
+            record.suite,
+            record.iteration,
+            record.duration_secs,
+            record.valid,
+            host_average_cpu,
+            host_peak_memory_kb,
+            process.map(|p| p.cpu_time.as_secs_f64()),
+            process.map(|p| p.average_cpu_usage),
+            process.map(|p| p.peak_rss_kb as i64),
+        ],
+    )?;
+    Ok(())
+}
+
+impl fmt::Display for SuiteTrend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.suite)?;
+        let mut previous: Option<f64> = None;
+        for run in &self.runs {
+            let d = &run.duration;
+            let change = previous
+                .map(|p| format!("{:+.1}%", (d.mean - p) / p * 100.0))
+                .unwrap_or_default();
+            writeln!(
+                f,
+                "  {}  {}  n={:<3} mean {:>7.1}s  median {:>7.1}s  cv {:>5.1}%  {}",
+                run.started_at,
+                run.run_uuid,
+                d.samples,
+                d.mean,
+                d.median,
+                d.cv * 100.0,
+                change
+            )?;
+            previous = Some(d.mean);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(store: &mut HistoryStore, uuid: &str, started_at: i64, durations: &[f64]) {
+        let transaction = store
+            .connection
+            .transaction()
+            .expect("Cannot start transaction");
+        let run = RunRecord {
+            uuid: uuid.to_owned(),
+            started_at,
+            working_dir: "/tmp".to_owned(),
+            host: None,
+        };
+        insert_run(&transaction, &run).expect("Cannot insert run");
+        for (idx, duration) in durations.iter().enumerate() {
+            let record = IterationRecord {
+                run_uuid: uuid,
+                suite: "adam",
+                iteration: idx as u32 + 1,
+                duration_secs: *duration,
+                valid: true,
+                system: None,
+                process: None,
+            };
+            insert_iteration(&transaction, record).expect("Cannot insert iteration");
+        }
+        transaction.commit().expect("Cannot commit");
+    }
+
+    #[test]
+    fn test_migrates_databases_without_valid_column() {
+        let connection = Connection::open_in_memory().expect("Cannot open database");
+        connection
+            .execute_batch(
+                "CREATE TABLE runs (
+                    uuid TEXT PRIMARY KEY,
+                    started_at INTEGER NOT NULL,
+                    working_dir TEXT NOT NULL,
+                    host TEXT
+                );
+                CREATE TABLE iterations (
+                    run_uuid TEXT NOT NULL REFERENCES runs(uuid),
+                    suite TEXT NOT NULL,
+                    iteration INTEGER NOT NULL,
+                    duration_secs REAL NOT NULL,
+                    host_average_cpu REAL,
+                    host_peak_memory_kb INTEGER,
+                    process_cpu_time_secs REAL,
+                    process_average_cpu REAL,
+                    process_peak_rss_kb INTEGER,
+                    PRIMARY KEY (run_uuid, suite, iteration)
+                );
+                INSERT INTO runs VALUES ('old', 100, '/tmp', NULL);
+                INSERT INTO iterations (run_uuid, suite, iteration, duration_secs)
+                    VALUES ('old', 'adam', 1, 100.0);",
+            )
+            .expect("Cannot create old schema");
+        let mut store = HistoryStore::with_connection(connection).expect("Cannot migrate");
+        insert(&mut store, "new", 200, &[110.0]);
+
+        let valid: Vec<bool> = store
+            .connection
+            .prepare("SELECT valid FROM iterations ORDER BY run_uuid")
+            .expect("Cannot prepare query")
+            .query_map(params![], |row| row.get(0))
+            .expect("Cannot query iterations")
+            .collect::<Result<_, _>>()
+            .expect("Cannot read valid column");
+        assert_eq!(vec![true, true], valid);
+
+        // Reopening does not apply the migration again
+        let store = HistoryStore::with_connection(store.connection).expect("Cannot reopen");
+        let version: i64 = store
+            .connection
+            .query_row("PRAGMA user_version", params![], |row| row.get(0))
+            .expect("Cannot read schema version");
+        assert_eq!(MIGRATIONS.len() as i64, version);
+    }
+
+    #[test]
+    fn test_trends_keep_last_runs_in_order() {
+        let connection = Connection::open_in_memory().expect("Cannot open database");
+        let mut store = HistoryStore::with_connection(connection).expect("Cannot create schema");
+        insert(&mut store, "second", 200, &[110.0, 112.0]);
+        insert(&mut store, "first", 100, &[100.0, 102.0]);
+        insert(&mut store, "third", 300, &[90.0, 92.0]);
+
+        let trends = store.trends(Some("adam"), 2).expect("Cannot query trends");
+        assert_eq!(1, trends.len());
+        let runs: Vec<(&str, f64)> = trends[0]
+            .runs
+            .iter()
+            .map(|r| (r.run_uuid.as_str(), r.duration.mean))
+            .collect();
+        assert_eq!(vec![("second", 111.0), ("third", 91.0)], runs);
+        assert!(store
+            .trends(Some("ddmlib"), 2)
+            .expect("Cannot query")
+            .is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+use roxmltree::{Document, Node};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct TestResults {
+    pub passed: u32,
+    pub failed: u32,
+    pub ignored: u32,
+    pub flaky: u32,
+    pub tests: Vec<TestCase>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Ignored,
+    // Failed at least once and passed on a retry
+    Flaky,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct TestCase {
+    pub class_name: String,
+    pub name: String,
+    // Sum of all attempts
+    pub duration: Duration,
+    pub attempts: u32,
+    pub status: TestStatus,
+}
+
+struct Attempt {
+    class_name: String,
+    name: String,
+    duration: Duration,
+    status: TestStatus,
+}
+
+// Runners write one file per device or pool and retried tests show up several times
+pub fn parse_reports(paths: &[PathBuf]) -> Result<TestResults, Box<dyn Error>> {
+    let mut attempts = vec![];
+    for path in paths {
+        let content = std::fs::read_to_string(path)?;
+        attempts.extend(parse_report(&content)?);
+    }
+    Ok(merge_attempts(attempts))
+}
+
+pub fn find_xml_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return files,
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(find_xml_files(&path));
+        } else if path.extension().map(|e| e == "xml").unwrap_or(false) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+fn parse_report(content: &str) -> Result<Vec<Attempt>, Box<dyn Error>> {
+    let document = Document::parse(content)?;
+    let root = document.root_element();
+    // Runners put other XML files (e.g. device info) next to the reports
+    if !matches!(root.tag_name().name(), "testsuite" | "testsuites") {
+        return Ok(vec![]);
+    }
+    Ok(root
+        .descendants()
+        .filter(|n| n.has_tag_name("testcase"))
+        .map(parse_test_case)
+        .collect())
+}
+
+fn parse_test_case(node: Node) -> Attempt {
+    let has_child = |name: &str| node.children().any(|c| c.has_tag_name(name));
+    let status = if has_child("skipped") {
+        TestStatus::Ignored
+    } else if has_child("failure") || has_child("error") {
+        TestStatus::Failed
+    } else if has_child("flakyFailure") || has_child("flakyError") {
+        TestStatus::Flaky
+    } else {
+        TestStatus::Passed
+    };
+    let seconds: f64 = node
+        .attribute("time")
+        .and_then(|t| t.replace(',', "").parse().ok())
+        .unwrap_or(0.0);
+    Attempt {
+        class_name: node.attribute("classname").unwrap_or_default().to_owned(),
+        name: node.attribute("name").unwrap_or_default().to_owned(),
+        duration: Duration::from_secs_f64(seconds.max(0.0)),
+        status,
+    }
+}
+
+fn merge_attempts(attempts: Vec<Attempt>) -> TestResults {
+    let mut merged: BTreeMap<(String, String), TestCase> = BTreeMap::new();
+    for attempt in attempts {
+        let key = (attempt.class_name.clone(), attempt.name.clone());
+        match merged.get_mut(&key) {
+            Some(test) => {
+                test.duration += attempt.duration;
+                test.attempts += 1;
+                test.status = merge_status(test.status, attempt.status);
+            }
+            None => {
+                merged.insert(
+                    key,
+                    TestCase {
+                        class_name: attempt.class_name,
+                        name: attempt.name,
+                        duration: attempt.duration,
+                        attempts: 1,
+                        status: attempt.status,
+                    },
+                );
+            }
+        }
+    }
+    let tests: Vec<TestCase> = merged.into_values().collect();
+    let count = |status: TestStatus| tests.iter().filter(|t| t.status == status).count() as u32;
+    TestResults {
+        passed: count(TestStatus::Passed),
+        failed: count(TestStatus::Failed),
+        ignored: count(TestStatus::Ignored),
+        flaky: count(TestStatus::Flaky),
+        tests,
+    }
+}
+
+fn merge_status(previous: TestStatus, next: TestStatus) -> TestStatus {
+    use TestStatus::*;
+    match (previous, next) {
+        (Ignored, other) | (other, Ignored) => other,
+        (Passed, Passed) => Passed,
+        (Failed, Failed) => Failed,
+        _ => Flaky,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_merge_attempts() {
+        let first_device = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuite name="device-1" tests="4" failures="1" skipped="1" time="3.5">
+  <testcase classname="com.example.LoginTest" name="login" time="1.5"/>
+  <testcase classname="com.example.LoginTest" name="logout" time="1.0">
+    <failure message="timeout">stacktrace</failure>
+  </testcase>
+  <testcase classname="com.example.LoginTest" name="signup" time="0.5">
+    <failure message="assertion">stacktrace</failure>
+  </testcase>
+  <testcase classname="com.example.LoginTest" name="ignored" time="0">
+    <skipped/>
+  </testcase>
+</testsuite>"#;
+        let second_device = r#"<testsuites><testsuite name="device-2">
+  <testcase classname="com.example.LoginTest" name="logout" time="1.25"/>
+  <testcase classname="com.example.LoginTest" name="signup" time="0.5">
+    <error message="crash"/>
+  </testcase>
+</testsuite></testsuites>"#;
+        let mut attempts = parse_report(first_device).expect("Cannot parse report");
+        attempts.extend(parse_report(second_device).expect("Cannot parse report"));
+        let results = merge_attempts(attempts);
+
+        assert_eq!(
+            (1, 1, 1, 1),
+            (
+                results.passed,
+                results.failed,
+                results.ignored,
+                results.flaky
+            )
+        );
+        let logout = results
+            .tests
+            .iter()
+            .find(|t| t.name == "logout")
+            .expect("Expected logout test");
+        assert_eq!(TestStatus::Flaky, logout.status);
+        assert_eq!(2, logout.attempts);
+        assert_eq!(Duration::from_millis(2250), logout.duration);
+    }
+
+    #[test]
+    fn test_ignore_non_junit_xml() {
+        let attempts = parse_report("<device><serial>emulator-5554</serial></device>")
+            .expect("Cannot parse report");
+        assert!(attempts.is_empty())
+    }
+}
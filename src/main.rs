@@ -1,20 +1,40 @@
+mod adb;
 mod adb_server;
+mod apk;
 mod benchmark_results;
 mod config;
 mod devices;
+mod docker;
 mod file_manager;
+mod history;
+mod junit;
 mod monitoring;
+mod network;
+mod random;
+mod regression;
+mod report;
 mod run_config;
+mod run_directory;
+mod shutdown;
+mod significance;
+mod statistics;
+mod summary;
 mod test_suite;
 mod testrunners;
 mod tools;
 
 use console::{style, Emoji};
 
+use crate::devices::{find_leaked_containers, remove_containers};
+use crate::history::HistoryStore;
+use crate::regression::RunComparison;
 use crate::run_config::RunConfig;
-use clap::{App, Arg};
-use config::Config;
-use std::time::Instant;
+use crate::run_directory::RunDirectory;
+use crate::shutdown::Shutdown;
+use crate::summary::RunSummary;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use config::{Comparison, Config, History};
+use std::path::{Path, PathBuf};
 use test_suite::TestSuiteRunner;
 use uuid::Uuid;
 
@@ -24,10 +44,6 @@ static PAPER: Emoji<'_, '_> = Emoji("📃  ", "");
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let run_uuid = Uuid::new_v4();
-    let start_time = Instant::now();
-    let current_dir = std::env::current_dir()?;
-    let run_config = RunConfig::new(run_uuid, current_dir, start_time);
     let matches = App::new("marathonbm")
         .version("0.1")
         .author("Ivan Balaksha <tagantroy@gmail.com>")
@@ -40,8 +56,110 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .help("Specify config name")
                 .takes_value(true),
         )
+        .subcommand(
+            SubCommand::with_name("report")
+                .about("Render a run directory as a self-contained HTML report")
+                .arg(
+                    Arg::with_name("RUN_DIR")
+                        .help("Run directory, <working dir>/<run id>")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Report file, defaults to <RUN_DIR>/report.html")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compare")
+                .about("Compare two runs and fail when a suite regressed or is missing")
+                .arg(
+                    Arg::with_name("BASELINE")
+                        .help("Baseline run directory")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("CANDIDATE")
+                        .help("Candidate run directory")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    Arg::with_name("threshold")
+                        .short("t")
+                        .long("threshold")
+                        .value_name("PERCENT")
+                        .help("Allowed slowdown of the mean duration, defaults to 5")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("confidence")
+                        .long("confidence")
+                        .value_name("LEVEL")
+                        .help("Confidence level of the intervals, defaults to 0.95")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("history")
+                .about("Show per-suite duration trends from the history database")
+                .arg(
+                    Arg::with_name("database")
+                        .short("d")
+                        .long("database")
+                        .value_name("FILE")
+                        .help("History database, defaults to history.sqlite")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("suite")
+                        .short("s")
+                        .long("suite")
+                        .value_name("NAME")
+                        .help("Only show this suite")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("limit")
+                        .short("n")
+                        .long("limit")
+                        .value_name("RUNS")
+                        .help("Number of most recent runs per suite, defaults to 10")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("cleanup")
+                .about("Remove emulator containers left behind by crashed runs")
+                .arg(
+                    Arg::with_name("run")
+                        .long("run")
+                        .value_name("RUN_ID")
+                        .help("Only remove containers of this run")
+                        .takes_value(true),
+                ),
+        )
         .get_matches();
 
+    match matches.subcommand() {
+        ("report", Some(args)) => report(args),
+        ("compare", Some(args)) => compare(args),
+        ("history", Some(args)) => history(args),
+        ("cleanup", Some(args)) => cleanup(args).await,
+        _ => run(&matches).await,
+    }
+}
+
+async fn run(matches: &ArgMatches<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let run_uuid = Uuid::new_v4();
+    let current_dir = std::env::current_dir()?;
+    let run_config = RunConfig::new(run_uuid, current_dir);
+
     println!("RUN ID: {}", &run_config.uuid);
     println!(
         "{} {}Processing configuration...",
@@ -49,10 +167,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         LOOKING_GLASS
     );
 
+    warn_about_leaked_containers().await;
+
     let config_path = matches.value_of("config").unwrap_or("default.yaml");
     let config_content = std::fs::read_to_string(config_path)?;
 
-    let config: Config = serde_yaml::from_str(&config_content)?;
+    let mut config: Config = serde_yaml::from_str(&config_content)?;
+    config::validate(&config)?;
 
     println!(
         "{} {}Running test suites...",
@@ -60,12 +181,114 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         TRUCK
     );
 
+    let comparison = config.comparison;
+    let history = config.history.take();
     let mut suites: Vec<TestSuiteRunner> = config.into();
 
+    let shutdown = Shutdown::listen();
     for suite in suites.iter_mut() {
-        suite.start(&run_config).await?;
+        suite.start(&run_config, &shutdown).await?;
     }
 
     println!("{} {}Saving results...", style("[3/3]").bold().dim(), PAPER);
+    let run_directory = RunDirectory::new(&run_config.working_dir, run_config.uuid);
+    let summary = RunSummary::new(&run_directory.load_execution_reports()?, &comparison);
+    run_directory.save_summary(&summary)?;
+    println!("{}", summary);
+    if let Some(history) = history {
+        HistoryStore::open(Path::new(&history.database))?.ingest(&run_config, &run_directory)?;
+    }
+    Ok(())
+}
+
+fn report(args: &ArgMatches<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let run_dir = PathBuf::from(args.value_of("RUN_DIR").expect("RUN_DIR is required"));
+    let output = args
+        .value_of("output")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| run_dir.join("report.html"));
+    let html = report::render_report(&RunDirectory::from_path(run_dir))?;
+    std::fs::write(&output, html)?;
+    println!("Report saved to {}", output.display());
+    Ok(())
+}
+
+fn compare(args: &ArgMatches<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let baseline = RunDirectory::from_path(PathBuf::from(
+        args.value_of("BASELINE").expect("BASELINE is required"),
+    ));
+    let candidate = RunDirectory::from_path(PathBuf::from(
+        args.value_of("CANDIDATE").expect("CANDIDATE is required"),
+    ));
+    let threshold: f64 = args.value_of("threshold").unwrap_or("5").parse()?;
+    let confidence: f64 = args.value_of("confidence").unwrap_or("0.95").parse()?;
+    if threshold < 0.0 || threshold.is_nan() {
+        return Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "Threshold has to be a percentage of at least 0, got {}",
+                threshold
+            ),
+        )));
+    }
+    if !(confidence > 0.0 && confidence < 1.0) {
+        return Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Confidence has to be between 0 and 1, got {}", confidence),
+        )));
+    }
+
+    let comparison = RunComparison::new(
+        &baseline.load_execution_reports()?,
+        &candidate.load_execution_reports()?,
+        threshold / 100.0,
+        Comparison::default().bootstrap_resamples,
+        confidence,
+    );
+    print!("{}", comparison);
+    if !comparison.passes() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn history(args: &ArgMatches<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let database = args
+        .value_of("database")
+        .map(str::to_owned)
+        .unwrap_or_else(|| History::default().database);
+    let limit: usize = args.value_of("limit").unwrap_or("10").parse()?;
+    let store = HistoryStore::open(Path::new(&database))?;
+    for trend in store.trends(args.value_of("suite"), limit)? {
+        println!("{}", trend);
+    }
     Ok(())
 }
+
+async fn cleanup(args: &ArgMatches<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let containers: Vec<_> = find_leaked_containers()
+        .await?
+        .into_iter()
+        .filter(|c| args.value_of("run").map(|run| c.run == run).unwrap_or(true))
+        .collect();
+    for container in &containers {
+        println!("Removing {}", container);
+    }
+    remove_containers(&containers).await?;
+    println!("Removed {} containers", containers.len());
+    Ok(())
+}
+
+// Leftovers of other runs compete with the new emulators for the host
+async fn warn_about_leaked_containers() {
+    if let Ok(containers) = find_leaked_containers().await {
+        if !containers.is_empty() {
+            println!(
+                "{} {} benchmark containers from previous runs are still running, \
+                 remove them with `marathonbm cleanup`",
+                style("WARNING").yellow().bold(),
+                containers.len()
+            );
+        }
+    }
+}
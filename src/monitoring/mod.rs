@@ -1,5 +1,6 @@
 mod process_monitoring;
+mod sampler;
 mod system_monitoring;
 
-pub use process_monitoring::ProcessMonitoring;
-pub use system_monitoring::SystemMonitoring;
+pub use process_monitoring::{ProcessMonitoring, ProcessMonitoringReport, ProcessSample};
+pub use system_monitoring::{SystemMonitoring, SystemMonitoringReport, SystemSample};
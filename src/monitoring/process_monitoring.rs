@@ -1,11 +1,295 @@
-pub struct ProcessMonitoring {}
+use crate::monitoring::sampler::Sampler;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProcessMonitoringReport {
+    pub interval: Duration,
+    pub summary: ProcessSummary,
+    pub samples: Vec<ProcessSample>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProcessSample {
+    pub elapsed: Duration,
+    pub processes: u32,
+    pub cpu_usage: f64,
+    pub cpu_time: Duration,
+    pub rss_kb: u64,
+    pub threads: u64,
+    pub open_fds: u64,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProcessSummary {
+    pub cpu_time: Duration,
+    pub peak_cpu_usage: f64,
+    pub average_cpu_usage: f64,
+    pub peak_rss_kb: u64,
+    pub average_rss_kb: u64,
+    pub peak_threads: u64,
+    pub average_threads: u64,
+    pub peak_open_fds: u64,
+    pub average_open_fds: u64,
+}
+
+impl ProcessSummary {
+    fn new(samples: &[ProcessSample]) -> Self {
+        if samples.is_empty() {
+            return ProcessSummary::default();
+        }
+        let count = samples.len() as u64;
+        let sum = |f: fn(&ProcessSample) -> u64| samples.iter().map(f).sum::<u64>();
+        let peak = |f: fn(&ProcessSample) -> u64| samples.iter().map(f).max().unwrap_or(0);
+        ProcessSummary {
+            cpu_time: samples.iter().map(|s| s.cpu_time).max().unwrap_or_default(),
+            peak_cpu_usage: samples.iter().map(|s| s.cpu_usage).fold(0.0, f64::max),
+            average_cpu_usage: samples.iter().map(|s| s.cpu_usage).sum::<f64>() / count as f64,
+            peak_rss_kb: peak(|s| s.rss_kb),
+            average_rss_kb: sum(|s| s.rss_kb) / count,
+            peak_threads: peak(|s| s.threads),
+            average_threads: sum(|s| s.threads) / count,
+            peak_open_fds: peak(|s| s.open_fds),
+            average_open_fds: sum(|s| s.open_fds) / count,
+        }
+    }
+}
+
+// Follows the runner process and every descendant it spawns (JVM, adb, ...)
+pub struct ProcessMonitoring {
+    interval: Duration,
+    sampler: Option<Sampler<ProcessSample>>,
+}
 
 impl ProcessMonitoring {
-    pub fn new() -> Self {
-        ProcessMonitoring {}
+    pub fn new(interval: Duration) -> Self {
+        ProcessMonitoring {
+            interval,
+            sampler: None,
+        }
     }
 }
 
 impl ProcessMonitoring {
-    pub fn start(&self) {}
+    pub fn start(&mut self, pid: u32) {
+        let mut probe = ProcessProbe::new(pid);
+        self.sampler = Some(Sampler::spawn(self.interval, move |elapsed| {
+            probe.sample(elapsed)
+        }));
+    }
+
+    pub async fn stop(&mut self) -> Result<ProcessMonitoringReport, Box<dyn Error>> {
+        let samples = match self.sampler.take() {
+            Some(sampler) => sampler.stop().await?,
+            None => vec![],
+        };
+        Ok(ProcessMonitoringReport {
+            interval: self.interval,
+            summary: ProcessSummary::new(&samples),
+            samples,
+        })
+    }
+}
+
+struct ProcessProbe {
+    root: u32,
+    ticks_per_second: u64,
+    page_size_kb: u64,
+    last_refresh: Instant,
+    // None until the tree was seen once, a delta against nothing would count its whole lifetime
+    last_cpu_ticks: Option<u64>,
+}
+
+impl ProcessProbe {
+    fn new(root: u32) -> Self {
+        // SAFETY: sysconf has no preconditions and only reads system configuration
+        let (ticks_per_second, page_size) = unsafe {
+            (
+                libc::sysconf(libc::_SC_CLK_TCK),
+                libc::sysconf(libc::_SC_PAGESIZE),
+            )
+        };
+        let stats = read_process_stats();
+        let tree = process_tree(root, &stats);
+        ProcessProbe {
+            root,
+            ticks_per_second: ticks_per_second.max(1) as u64,
+            page_size_kb: (page_size.max(1024) / 1024) as u64,
+            last_refresh: Instant::now(),
+            last_cpu_ticks: if tree.is_empty() {
+                None
+            } else {
+                Some(tree.iter().map(|s| s.cpu_ticks()).sum())
+            },
+        }
+    }
+
+    // Percent of one core used since the last sample
+    fn cpu_usage(&mut self, cpu_ticks: u64, now: Instant) -> f64 {
+        let seconds = (now - self.last_refresh).as_secs_f64();
+        let last_cpu_ticks = self.last_cpu_ticks.replace(cpu_ticks);
+        self.last_refresh = now;
+        match last_cpu_ticks {
+            Some(last_cpu_ticks) if seconds > 0.0 => {
+                let used_seconds =
+                    cpu_ticks.saturating_sub(last_cpu_ticks) as f64 / self.ticks_per_second as f64;
+                used_seconds / seconds * 100.0
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn sample(&mut self, elapsed: Duration) -> ProcessSample {
+        let stats = read_process_stats();
+        let tree = process_tree(self.root, &stats);
+
+        let cpu_ticks: u64 = tree.iter().map(|s| s.cpu_ticks()).sum();
+
+        ProcessSample {
+            elapsed,
+            processes: tree.len() as u32,
+            cpu_usage: self.cpu_usage(cpu_ticks, Instant::now()),
+            cpu_time: Duration::from_millis(cpu_ticks * 1000 / self.ticks_per_second),
+            rss_kb: tree.iter().map(|s| s.rss_pages).sum::<u64>() * self.page_size_kb,
+            threads: tree.iter().map(|s| s.threads).sum(),
+            open_fds: tree.iter().map(|s| count_open_fds(s.pid)).sum(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct ProcessStat {
+    pid: u32,
+    parent: u32,
+    utime: u64,
+    stime: u64,
+    cutime: u64,
+    cstime: u64,
+    threads: u64,
+    rss_pages: u64,
+}
+
+impl ProcessStat {
+    // Children that already exited are accounted in cutime/cstime of their parent
+    fn cpu_ticks(&self) -> u64 {
+        self.utime + self.stime + self.cutime + self.cstime
+    }
+}
+
+fn read_process_stats() -> Vec<ProcessStat> {
+    let entries = match std::fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|pid| std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok())
+        .filter_map(|content| parse_process_stat(&content))
+        .collect()
+}
+
+fn count_open_fds(pid: u32) -> u64 {
+    std::fs::read_dir(format!("/proc/{}/fd", pid))
+        .map(|entries| entries.count() as u64)
+        .unwrap_or(0)
+}
+
+// See proc(5), the command name may contain spaces and parentheses
+fn parse_process_stat(content: &str) -> Option<ProcessStat> {
+    let (pid, rest) = content.split_at(content.find(" (")?);
+    let fields: Vec<&str> = rest[rest.rfind(')')? + 1..].split_whitespace().collect();
+    let field = |idx: usize| -> Option<u64> { fields.get(idx)?.parse().ok() };
+    Some(ProcessStat {
+        pid: pid.trim().parse().ok()?,
+        parent: field(1)? as u32,
+        utime: field(11)?,
+        stime: field(12)?,
+        cutime: field(13)?,
+        cstime: field(14)?,
+        threads: field(17)?,
+        rss_pages: field(21)?,
+    })
+}
+
+fn process_tree(root: u32, stats: &[ProcessStat]) -> Vec<&ProcessStat> {
+    let mut children: HashMap<u32, Vec<&ProcessStat>> = HashMap::new();
+    for stat in stats {
+        children.entry(stat.parent).or_default().push(stat);
+    }
+    let mut tree: Vec<&ProcessStat> = stats.iter().filter(|s| s.pid == root).collect();
+    let mut idx = 0;
+    while idx < tree.len() {
+        if let Some(descendants) = children.get(&tree[idx].pid) {
+            tree.extend(descendants);
+        }
+        idx += 1;
+    }
+    tree
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(pid: u32, parent: u32) -> ProcessStat {
+        ProcessStat {
+            pid,
+            parent,
+            utime: 0,
+            stime: 0,
+            cutime: 0,
+            cstime: 0,
+            threads: 1,
+            rss_pages: 0,
+        }
+    }
+
+    #[test]
+    fn test_parse_process_stat() {
+        let content = "4242 (java (main)) S 4200 4242 4200 0 -1 4194560 1000 0 0 0 \
+                       150 50 10 5 20 0 42 0 12345 1000000 2048 18446744073709551615";
+        let expected = ProcessStat {
+            pid: 4242,
+            parent: 4200,
+            utime: 150,
+            stime: 50,
+            cutime: 10,
+            cstime: 5,
+            threads: 42,
+            rss_pages: 2048,
+        };
+        assert_eq!(Some(expected), parse_process_stat(content))
+    }
+
+    #[test]
+    fn test_process_tree() {
+        let stats = vec![
+            stat(1, 0),
+            stat(10, 1),
+            stat(11, 10),
+            stat(12, 11),
+            stat(20, 1),
+            stat(13, 10),
+        ];
+        let mut pids: Vec<u32> = process_tree(10, &stats).iter().map(|s| s.pid).collect();
+        pids.sort_unstable();
+        assert_eq!(vec![10, 11, 12, 13], pids)
+    }
+
+    #[test]
+    fn test_first_delta_without_seed_is_skipped() {
+        let mut probe = ProcessProbe::new(u32::MAX);
+        assert_eq!(None, probe.last_cpu_ticks);
+        let start = probe.last_refresh;
+        let ticks = probe.ticks_per_second;
+        assert_eq!(
+            0.0,
+            probe.cpu_usage(ticks * 100, start + Duration::from_secs(1))
+        );
+        let usage = probe.cpu_usage(ticks * 101, start + Duration::from_secs(2));
+        assert!((usage - 100.0).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,72 @@
+use std::error::Error;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+// Calls `sample` every `interval` on a background task until stopped. The task is aborted when
+// the sampler is dropped without being stopped, e.g. by an interrupted iteration
+pub struct Sampler<S> {
+    stop: Option<oneshot::Sender<()>>,
+    handle: JoinHandle<Vec<S>>,
+}
+
+impl<S: Send + 'static> Sampler<S> {
+    pub fn spawn<F>(interval: Duration, mut sample: F) -> Self
+    where
+        F: FnMut(Duration) -> S + Send + 'static,
+    {
+        let (stop, mut stopped) = oneshot::channel::<()>();
+        let handle = tokio::spawn(async move {
+            let start = Instant::now();
+            let mut ticker = tokio::time::interval(interval);
+            let mut samples = Vec::new();
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => samples.push(sample(start.elapsed())),
+                    _ = &mut stopped => break,
+                }
+            }
+            samples
+        });
+        Sampler {
+            stop: Some(stop),
+            handle,
+        }
+    }
+
+    pub async fn stop(mut self) -> Result<Vec<S>, Box<dyn Error>> {
+        if let Some(stop) = self.stop.take() {
+            // The receiver is only gone if the task has already finished
+            let _ = stop.send(());
+        }
+        Ok((&mut self.handle).await?)
+    }
+}
+
+impl<S> Drop for Sampler<S> {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_dropped_sampler_stops_sampling() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = count.clone();
+        let sampler = Sampler::spawn(Duration::from_millis(5), move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        drop(sampler);
+        let sampled = count.load(Ordering::SeqCst);
+        assert!(sampled > 0);
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(sampled, count.load(Ordering::SeqCst));
+    }
+}
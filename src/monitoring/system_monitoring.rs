@@ -1,11 +1,210 @@
-pub struct SystemMonitoring {}
+use crate::monitoring::sampler::Sampler;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use sysinfo::{NetworkExt, NetworksExt, ProcessorExt, RefreshKind, System, SystemExt};
+
+const SECTOR_SIZE: u64 = 512;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SystemMonitoringReport {
+    pub interval: Duration,
+    pub samples: Vec<SystemSample>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SystemSample {
+    pub elapsed: Duration,
+    pub cpu_per_core: Vec<f32>,
+    pub load_average: LoadAverage,
+    pub memory_total_kb: u64,
+    pub memory_used_kb: u64,
+    pub swap_total_kb: u64,
+    pub swap_used_kb: u64,
+    pub disk_read_bytes_per_sec: u64,
+    pub disk_written_bytes_per_sec: u64,
+    pub net_received_bytes_per_sec: u64,
+    pub net_transmitted_bytes_per_sec: u64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct LoadAverage {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+}
+
+pub struct SystemMonitoring {
+    interval: Duration,
+    sampler: Option<Sampler<SystemSample>>,
+}
 
 impl SystemMonitoring {
-    pub fn new() -> Self {
-        SystemMonitoring {}
+    pub fn new(interval: Duration) -> Self {
+        SystemMonitoring {
+            interval,
+            sampler: None,
+        }
     }
 }
 
 impl SystemMonitoring {
-    pub fn start(&self) {}
+    pub fn start(&mut self) {
+        let mut probe = SystemProbe::new();
+        self.sampler = Some(Sampler::spawn(self.interval, move |elapsed| {
+            probe.sample(elapsed)
+        }));
+    }
+
+    pub async fn stop(&mut self) -> Result<SystemMonitoringReport, Box<dyn Error>> {
+        let samples = match self.sampler.take() {
+            Some(sampler) => sampler.stop().await?,
+            None => vec![],
+        };
+        Ok(SystemMonitoringReport {
+            interval: self.interval,
+            samples,
+        })
+    }
+}
+
+struct SystemProbe {
+    system: System,
+    last_refresh: Instant,
+    last_disk_stats: DiskStats,
+}
+
+impl SystemProbe {
+    fn new() -> Self {
+        let refresh = RefreshKind::new()
+            .with_cpu()
+            .with_memory()
+            .with_networks_list()
+            .with_networks();
+        SystemProbe {
+            system: System::new_with_specifics(refresh),
+            last_refresh: Instant::now(),
+            last_disk_stats: read_disk_stats(),
+        }
+    }
+
+    fn sample(&mut self, elapsed: Duration) -> SystemSample {
+        self.system.refresh_cpu();
+        self.system.refresh_memory();
+        self.system.refresh_networks();
+        let disk_stats = read_disk_stats();
+
+        let now = Instant::now();
+        let seconds = (now - self.last_refresh).as_secs_f64();
+        self.last_refresh = now;
+
+        let (received, transmitted) =
+            self.system
+                .get_networks()
+                .iter()
+                .fold((0, 0), |(received, transmitted), (_, data)| {
+                    (
+                        received + data.get_received(),
+                        transmitted + data.get_transmitted(),
+                    )
+                });
+        let read = disk_stats
+            .read_bytes
+            .saturating_sub(self.last_disk_stats.read_bytes);
+        let written = disk_stats
+            .written_bytes
+            .saturating_sub(self.last_disk_stats.written_bytes);
+        self.last_disk_stats = disk_stats;
+
+        let load_average = self.system.get_load_average();
+        SystemSample {
+            elapsed,
+            cpu_per_core: self
+                .system
+                .get_processors()
+                .iter()
+                .map(|p| p.get_cpu_usage())
+                .collect(),
+            load_average: LoadAverage {
+                one: load_average.one,
+                five: load_average.five,
+                fifteen: load_average.fifteen,
+            },
+            memory_total_kb: self.system.get_total_memory(),
+            memory_used_kb: self.system.get_used_memory(),
+            swap_total_kb: self.system.get_total_swap(),
+            swap_used_kb: self.system.get_used_swap(),
+            disk_read_bytes_per_sec: per_second(read, seconds),
+            disk_written_bytes_per_sec: per_second(written, seconds),
+            net_received_bytes_per_sec: per_second(received, seconds),
+            net_transmitted_bytes_per_sec: per_second(transmitted, seconds),
+        }
+    }
+}
+
+fn per_second(value: u64, seconds: f64) -> u64 {
+    if seconds > 0.0 {
+        (value as f64 / seconds) as u64
+    } else {
+        0
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct DiskStats {
+    read_bytes: u64,
+    written_bytes: u64,
+}
+
+// sysinfo has no host-wide disk IO counters, so read them from the kernel directly
+fn read_disk_stats() -> DiskStats {
+    std::fs::read_to_string("/proc/diskstats")
+        .map(|content| parse_disk_stats(&content, is_whole_disk))
+        .unwrap_or_default()
+}
+
+// Partitions are listed in /proc/diskstats as well, only whole disks live in /sys/block
+fn is_whole_disk(name: &str) -> bool {
+    Path::new("/sys/block").join(name).exists()
+}
+
+fn parse_disk_stats<F>(content: &str, include: F) -> DiskStats
+where
+    F: Fn(&str) -> bool,
+{
+    content
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<&str>>())
+        .filter(|fields| fields.len() >= 10 && include(fields[2]))
+        .fold(DiskStats::default(), |acc, fields| {
+            let sectors_read: u64 = fields[5].parse().unwrap_or(0);
+            let sectors_written: u64 = fields[9].parse().unwrap_or(0);
+            DiskStats {
+                read_bytes: acc.read_bytes + sectors_read * SECTOR_SIZE,
+                written_bytes: acc.written_bytes + sectors_written * SECTOR_SIZE,
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_disk_stats() {
+        let content = "\
+ 259       0 nvme0n1 1000 10 2000 300 500 20 4000 600 0 700 900 0 0 0 0
+ 259       1 nvme0n1p1 900 10 1800 300 400 20 3000 600 0 700 900 0 0 0 0
+   8       0 sda 10 0 20 1 5 0 40 2 0 3 4
+";
+        let stats = parse_disk_stats(content, |name| !name.ends_with("p1"));
+        assert_eq!(
+            DiskStats {
+                read_bytes: (2000 + 20) * SECTOR_SIZE,
+                written_bytes: (4000 + 40) * SECTOR_SIZE,
+            },
+            stats
+        )
+    }
 }
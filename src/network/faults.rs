@@ -0,0 +1,266 @@
+use crate::config::{Fault, FaultInjection, FaultRule};
+use crate::random::XorShift;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+// A fault as it hit a device, offsets are relative to the start of the test run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InjectedFault {
+    pub serial: String,
+    pub after: Duration,
+    pub fault: Fault,
+    // False for faults which fired by chance
+    pub scheduled: bool,
+}
+
+// Shared by all links of a provider so faults end up in one report
+pub type FaultLog = Arc<Mutex<Vec<InjectedFault>>>;
+
+// What the connections of a link have to do about a fault
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkAction {
+    Drop,
+    Stall(Duration),
+}
+
+enum Availability {
+    Up,
+    DownUntil(Instant),
+    Down,
+}
+
+// Faults configured for the device with the given index of a suite
+pub struct DeviceFaults {
+    pub device: u32,
+    pub injection: FaultInjection,
+    pub log: FaultLog,
+}
+
+// Decides which faults hit one device link. Nothing fires until the link is armed
+pub struct FaultInjector {
+    serial: String,
+    rules: Vec<FaultRule>,
+    log: FaultLog,
+    state: Mutex<InjectorState>,
+}
+
+struct InjectorState {
+    armed_at: Option<Instant>,
+    rng: XorShift,
+    availability: Availability,
+}
+
+impl FaultInjector {
+    pub fn new(serial: String, faults: DeviceFaults) -> Self {
+        let rng = match faults.injection.seed {
+            Some(seed) => XorShift::new(seed.wrapping_add(faults.device as u64)),
+            None => XorShift::from_time(),
+        };
+        let device = faults.device;
+        FaultInjector {
+            serial,
+            rules: faults
+                .injection
+                .rules
+                .into_iter()
+                .filter(|rule| rule.devices.is_empty() || rule.devices.contains(&device))
+                .collect(),
+            log: faults.log,
+            state: Mutex::new(InjectorState {
+                armed_at: None,
+                rng,
+                availability: Availability::Up,
+            }),
+        }
+    }
+
+    pub fn arm(&self) -> Instant {
+        let mut state = self.state.lock().expect("Fault injector is poisoned");
+        let now = Instant::now();
+        state.armed_at = Some(now);
+        state.availability = Availability::Up;
+        now
+    }
+
+    // Devices come back once the test run is over
+    pub fn disarm(&self) {
+        let mut state = self.state.lock().expect("Fault injector is poisoned");
+        state.armed_at = None;
+        state.availability = Availability::Up;
+    }
+
+    // Offsets and indices of the rules which fire at a fixed time, earliest first
+    pub fn schedule(&self) -> Vec<(Duration, usize)> {
+        let mut schedule: Vec<_> = self
+            .rules
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, rule)| rule.at_secs.map(|at| (Duration::from_secs(at), idx)))
+            .collect();
+        schedule.sort();
+        schedule
+    }
+
+    pub fn fire(&self, rule: usize) -> Option<LinkAction> {
+        let mut state = self.state.lock().expect("Fault injector is poisoned");
+        let fault = self.rules[rule].fault;
+        self.inject(&mut state, fault, true)
+    }
+
+    // Called for every chunk forwarded, at most one random fault hits it
+    pub fn roll(&self) -> Option<LinkAction> {
+        let mut state = self.state.lock().expect("Fault injector is poisoned");
+        state.armed_at?;
+        let fault = self.rules.iter().find_map(|rule| {
+            let probability = rule.probability?;
+            if state.rng.unit() < probability {
+                Some(rule.fault)
+            } else {
+                None
+            }
+        })?;
+        self.inject(&mut state, fault, false)
+    }
+
+    pub fn accepts(&self) -> bool {
+        let mut state = self.state.lock().expect("Fault injector is poisoned");
+        match state.availability {
+            Availability::Up => true,
+            Availability::DownUntil(until) if Instant::now() >= until => {
+                state.availability = Availability::Up;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn inject(
+        &self,
+        state: &mut InjectorState,
+        fault: Fault,
+        scheduled: bool,
+    ) -> Option<LinkAction> {
+        let armed_at = state.armed_at?;
+        let now = Instant::now();
+        self.log
+            .lock()
+            .expect("Fault log is poisoned")
+            .push(InjectedFault {
+                serial: self.serial.clone(),
+                after: now - armed_at,
+                fault,
+                scheduled,
+            });
+        Some(match fault {
+            Fault::DropConnections => LinkAction::Drop,
+            Fault::StallReads { duration_ms } => {
+                LinkAction::Stall(Duration::from_millis(duration_ms))
+            }
+            Fault::Disconnect { duration_secs } => {
+                state.availability = match duration_secs {
+                    Some(secs) => Availability::DownUntil(now + Duration::from_secs(secs)),
+                    None => Availability::Down,
+                };
+                LinkAction::Drop
+            }
+        })
+    }
+}
+
+// Rules need exactly one trigger
+pub fn is_valid(rule: &FaultRule) -> bool {
+    match (rule.at_secs, rule.probability) {
+        (Some(_), None) => true,
+        (None, Some(probability)) => (0.0..=1.0).contains(&probability),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn faults(device: u32, rules: Vec<FaultRule>, log: &FaultLog) -> DeviceFaults {
+        DeviceFaults {
+            device,
+            injection: FaultInjection {
+                seed: Some(1),
+                rules,
+            },
+            log: log.clone(),
+        }
+    }
+
+    fn rule(fault: Fault, at_secs: Option<u64>, probability: Option<f64>) -> FaultRule {
+        FaultRule {
+            fault,
+            at_secs,
+            probability,
+            devices: vec![],
+        }
+    }
+
+    #[test]
+    fn test_disconnect_refuses_until_disarmed() {
+        let log = FaultLog::default();
+        let injector = FaultInjector::new(
+            "localhost:5555".to_owned(),
+            faults(
+                0,
+                vec![rule(
+                    Fault::Disconnect {
+                        duration_secs: None,
+                    },
+                    Some(10),
+                    None,
+                )],
+                &log,
+            ),
+        );
+        // Faults before the test run are not injected
+        assert_eq!(None, injector.fire(0));
+
+        injector.arm();
+        assert_eq!(vec![(Duration::from_secs(10), 0)], injector.schedule());
+        assert_eq!(Some(LinkAction::Drop), injector.fire(0));
+        assert!(!injector.accepts());
+        injector.disarm();
+        assert!(injector.accepts());
+
+        let log = log.lock().unwrap();
+        assert_eq!(1, log.len());
+        assert!(log[0].scheduled);
+    }
+
+    #[test]
+    fn test_roll_only_applies_device_rules() {
+        let mut stall = rule(Fault::StallReads { duration_ms: 100 }, None, Some(1.0));
+        stall.devices = vec![1];
+        let log = FaultLog::default();
+        let other = FaultInjector::new(
+            "localhost:1".to_owned(),
+            faults(0, vec![stall.clone()], &log),
+        );
+        let target = FaultInjector::new("localhost:2".to_owned(), faults(1, vec![stall], &log));
+        other.arm();
+        target.arm();
+
+        assert_eq!(None, other.roll());
+        assert_eq!(
+            Some(LinkAction::Stall(Duration::from_millis(100))),
+            target.roll()
+        );
+        assert_eq!("localhost:2", log.lock().unwrap()[0].serial);
+    }
+
+    #[test]
+    fn test_is_valid() {
+        assert!(is_valid(&rule(Fault::DropConnections, Some(1), None)));
+        assert!(is_valid(&rule(Fault::DropConnections, None, Some(0.5))));
+        assert!(!is_valid(&rule(Fault::DropConnections, None, None)));
+        assert!(!is_valid(&rule(Fault::DropConnections, Some(1), Some(0.5))));
+        assert!(!is_valid(&rule(Fault::DropConnections, None, Some(2.0))));
+    }
+}
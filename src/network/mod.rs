@@ -0,0 +1,11 @@
+mod faults;
+
+mod proxy;
+
+mod shaping;
+
+pub use faults::is_valid;
+pub use faults::DeviceFaults;
+pub use faults::FaultLog;
+pub use faults::InjectedFault;
+pub use proxy::LinkProxy;
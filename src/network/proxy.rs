@@ -0,0 +1,350 @@
+use crate::config::NetworkProfile;
+use crate::network::faults::{DeviceFaults, FaultInjector, LinkAction};
+use crate::network::shaping::Shaper;
+use crate::random::XorShift;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+const CHUNK_SIZE: usize = 16 * 1024;
+// Chunks in flight per direction before reading blocks
+const QUEUE_SIZE: usize = 64;
+// Scheduled faults pending for connections which are busy
+const ACTION_QUEUE_SIZE: usize = 16;
+
+// Listens on a local port and forwards every connection to the target through a shaped link,
+// injecting faults while armed. Connections are cut when the proxy is dropped
+pub struct LinkProxy {
+    port: u16,
+    stop: watch::Sender<bool>,
+    faults: Option<Arc<FaultInjector>>,
+    actions: broadcast::Sender<LinkAction>,
+    schedule: Option<JoinHandle<()>>,
+}
+
+impl LinkProxy {
+    pub async fn start(
+        target: SocketAddr,
+        profile: NetworkProfile,
+        faults: Option<DeviceFaults>,
+    ) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let port = listener.local_addr()?.port();
+        // Faults are logged under the serial adb knows the device by
+        let faults = faults
+            .map(|faults| Arc::new(FaultInjector::new(format!("localhost:{}", port), faults)));
+        let (stop, stopped) = watch::channel(false);
+        let (actions, _) = broadcast::channel(ACTION_QUEUE_SIZE);
+        let link = Link {
+            target,
+            profile,
+            faults: faults.clone(),
+            actions: actions.clone(),
+        };
+        tokio::spawn(accept(listener, link, stopped));
+        Ok(LinkProxy {
+            port,
+            stop,
+            faults,
+            actions,
+            schedule: None,
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    // Starts injecting faults, scheduled ones count from now
+    pub fn arm(&mut self) {
+        self.disarm();
+        let faults = match &self.faults {
+            Some(faults) => faults.clone(),
+            None => return,
+        };
+        let armed_at = faults.arm();
+        let actions = self.actions.clone();
+        self.schedule = Some(tokio::spawn(async move {
+            for (after, rule) in faults.schedule() {
+                tokio::time::sleep_until(armed_at + after).await;
+                if let Some(action) = faults.fire(rule) {
+                    // Nobody listens when no connection is open
+                    let _ = actions.send(action);
+                }
+            }
+        }));
+    }
+
+    pub fn disarm(&mut self) {
+        if let Some(schedule) = self.schedule.take() {
+            schedule.abort();
+        }
+        if let Some(faults) = &self.faults {
+            faults.disarm();
+        }
+    }
+}
+
+impl Drop for LinkProxy {
+    fn drop(&mut self) {
+        self.disarm();
+        let _ = self.stop.send(true);
+    }
+}
+
+#[derive(Clone)]
+struct Link {
+    target: SocketAddr,
+    profile: NetworkProfile,
+    faults: Option<Arc<FaultInjector>>,
+    actions: broadcast::Sender<LinkAction>,
+}
+
+async fn accept(listener: TcpListener, link: Link, mut stopped: watch::Receiver<bool>) {
+    loop {
+        let inbound = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((inbound, _)) => inbound,
+                Err(_) => continue,
+            },
+            _ = stopped.changed() => return,
+        };
+        // A disconnected device refuses connections, dropping the stream closes it right away
+        if !link.faults.as_ref().is_none_or(|faults| faults.accepts()) {
+            continue;
+        }
+        let link = link.clone();
+        let mut stopped = stopped.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = forward(inbound, link) => {},
+                _ = stopped.changed() => {},
+            }
+        });
+    }
+}
+
+// A pump hit a fault which cuts the connection
+struct Dropped;
+
+// State both directions of one connection share
+struct Connection {
+    faults: Option<Arc<FaultInjector>>,
+    stalled_until: Mutex<Option<Instant>>,
+}
+
+impl Connection {
+    fn apply(&self, action: LinkAction) -> Result<(), Dropped> {
+        match action {
+            LinkAction::Drop => Err(Dropped),
+            LinkAction::Stall(duration) => {
+                *self.stalled_until.lock().expect("Connection is poisoned") =
+                    Some(Instant::now() + duration);
+                Ok(())
+            }
+        }
+    }
+
+    fn roll(&self) -> Result<(), Dropped> {
+        match self.faults.as_ref().and_then(|faults| faults.roll()) {
+            Some(action) => self.apply(action),
+            None => Ok(()),
+        }
+    }
+
+    // Waits for the release time, and for a stall which may begin while waiting
+    async fn wait(&self, release_at: Instant) {
+        loop {
+            let stalled_until = *self.stalled_until.lock().expect("Connection is poisoned");
+            let until = stalled_until.map_or(release_at, |stalled| stalled.max(release_at));
+            if Instant::now() >= until {
+                return;
+            }
+            tokio::time::sleep_until(until).await;
+        }
+    }
+}
+
+async fn forward(inbound: TcpStream, link: Link) {
+    let mut actions = link.actions.subscribe();
+    let outbound = match TcpStream::connect(link.target).await {
+        Ok(outbound) => outbound,
+        // Dropping the inbound side looks like a refused device to adb
+        Err(_) => return,
+    };
+    let connection = Connection {
+        faults: link.faults,
+        stalled_until: Mutex::new(None),
+    };
+    let (inbound_read, inbound_write) = inbound.into_split();
+    let (outbound_read, outbound_write) = outbound.into_split();
+    let upstream = Shaper::new(link.profile.clone(), XorShift::from_time());
+    let downstream = Shaper::new(link.profile, XorShift::from_time());
+    let pumps = async {
+        tokio::try_join!(
+            pump(inbound_read, outbound_write, upstream, &connection),
+            pump(outbound_read, inbound_write, downstream, &connection),
+        )
+    };
+    tokio::pin!(pumps);
+    loop {
+        tokio::select! {
+            _ = &mut pumps => return,
+            action = actions.recv() => match action {
+                Ok(action) => {
+                    if connection.apply(action).is_err() {
+                        return;
+                    }
+                }
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => {
+                    let _ = pumps.await;
+                    return;
+                }
+            },
+        }
+    }
+}
+
+// Reading and writing run concurrently so latency delays chunks without throttling throughput
+async fn pump(
+    mut from: OwnedReadHalf,
+    mut to: OwnedWriteHalf,
+    mut shaper: Shaper,
+    connection: &Connection,
+) -> Result<(), Dropped> {
+    let (sender, mut receiver) = mpsc::channel::<(Instant, Vec<u8>)>(QUEUE_SIZE);
+    let read = async move {
+        let mut buffer = vec![0; CHUNK_SIZE];
+        loop {
+            let read = match from.read(&mut buffer).await {
+                Ok(0) | Err(_) => return Ok(()),
+                Ok(read) => read,
+            };
+            connection.roll()?;
+            let release_at = shaper.release_at(Instant::now(), read);
+            if sender
+                .send((release_at, buffer[..read].to_vec()))
+                .await
+                .is_err()
+            {
+                return Ok(());
+            }
+        }
+    };
+    let write = async move {
+        while let Some((release_at, chunk)) = receiver.recv().await {
+            connection.wait(release_at).await;
+            if to.write_all(&chunk).await.is_err() {
+                return Ok(());
+            }
+        }
+        let _ = to.shutdown().await;
+        Ok(())
+    };
+    tokio::try_join!(read, write).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Fault, FaultInjection, FaultRule};
+    use crate::network::faults::FaultLog;
+    use std::time::Duration;
+
+    // How long a scheduled fault takes to reach connections at most
+    const SCHEDULE_SLACK: Duration = Duration::from_millis(500);
+
+    async fn echo_server() -> SocketAddr {
+        let echo = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .expect("Cannot bind");
+        let target = echo.local_addr().expect("No address");
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = echo.accept().await {
+                tokio::spawn(async move {
+                    let mut buffer = [0; 4];
+                    while stream.read_exact(&mut buffer).await.is_ok() {
+                        if stream.write_all(&buffer).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        target
+    }
+
+    #[tokio::test]
+    async fn test_forwards_with_latency() {
+        let profile = NetworkProfile {
+            latency_ms: 50,
+            jitter_ms: 0,
+            bandwidth_kbps: None,
+            loss_percent: 0.0,
+        };
+        let proxy = LinkProxy::start(echo_server().await, profile, None)
+            .await
+            .expect("Cannot start proxy");
+
+        let started_at = Instant::now();
+        let mut stream = TcpStream::connect(("127.0.0.1", proxy.port()))
+            .await
+            .expect("Cannot connect");
+        stream.write_all(b"ping").await.expect("Cannot write");
+        let mut reply = [0; 4];
+        stream.read_exact(&mut reply).await.expect("Cannot read");
+        assert_eq!(b"ping", &reply);
+        // One way latency in both directions
+        assert!(started_at.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_disconnect() {
+        let log = FaultLog::default();
+        let faults = DeviceFaults {
+            device: 0,
+            injection: FaultInjection {
+                seed: Some(1),
+                rules: vec![FaultRule {
+                    fault: Fault::Disconnect {
+                        duration_secs: None,
+                    },
+                    at_secs: Some(0),
+                    probability: None,
+                    devices: vec![],
+                }],
+            },
+            log: log.clone(),
+        };
+        let mut proxy =
+            LinkProxy::start(echo_server().await, NetworkProfile::default(), Some(faults))
+                .await
+                .expect("Cannot start proxy");
+        let mut stream = TcpStream::connect(("127.0.0.1", proxy.port()))
+            .await
+            .expect("Cannot connect");
+        stream.write_all(b"ping").await.expect("Cannot write");
+        let mut reply = [0; 4];
+        stream.read_exact(&mut reply).await.expect("Cannot read");
+
+        proxy.arm();
+        let mut rest = vec![];
+        let closed = tokio::time::timeout(SCHEDULE_SLACK, stream.read_to_end(&mut rest)).await;
+        assert!(closed.is_ok(), "Connection is still open");
+        assert_eq!(1, log.lock().unwrap().len());
+
+        // New connections are refused while the device is away
+        let mut refused = TcpStream::connect(("127.0.0.1", proxy.port()))
+            .await
+            .expect("Cannot connect");
+        let _ = refused.write_all(b"ping").await;
+        assert!(refused.read_exact(&mut reply).await.is_err());
+    }
+}
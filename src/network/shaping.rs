@@ -0,0 +1,101 @@
+use crate::config::NetworkProfile;
+use crate::random::XorShift;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// TCP never loses data, a lost segment shows up as a retransmission timeout instead
+const MIN_RETRANSMISSION_TIMEOUT: Duration = Duration::from_millis(200);
+
+// Decides when a chunk read on one side of the link may be written to the other
+pub struct Shaper {
+    profile: NetworkProfile,
+    rng: XorShift,
+    line_free_at: Option<Instant>,
+    last_release: Option<Instant>,
+}
+
+impl Shaper {
+    pub fn new(profile: NetworkProfile, rng: XorShift) -> Self {
+        Shaper {
+            profile,
+            rng,
+            line_free_at: None,
+            last_release: None,
+        }
+    }
+
+    pub fn release_at(&mut self, read_at: Instant, bytes: usize) -> Instant {
+        // Serialization delay, chunks queue up behind each other on a capped link
+        let sent_at = match self.profile.bandwidth_kbps {
+            Some(kbps) if kbps > 0 => {
+                let start = self.line_free_at.map_or(read_at, |free| free.max(read_at));
+                let transmission =
+                    Duration::from_secs_f64(bytes as f64 * 8.0 / (kbps as f64 * 1000.0));
+                self.line_free_at = Some(start + transmission);
+                start + transmission
+            }
+            _ => read_at,
+        };
+        let mut delay = self.propagation_delay();
+        if self.rng.unit() * 100.0 < self.profile.loss_percent {
+            delay +=
+                MIN_RETRANSMISSION_TIMEOUT.max(Duration::from_millis(self.profile.latency_ms * 2));
+        }
+        // Jitter must not reorder the byte stream
+        let release = self
+            .last_release
+            .map_or(sent_at + delay, |last| last.max(sent_at + delay));
+        self.last_release = Some(release);
+        release
+    }
+
+    fn propagation_delay(&mut self) -> Duration {
+        let jitter = self.profile.jitter_ms as i64;
+        let offset = if jitter > 0 {
+            self.rng.below((jitter * 2 + 1) as usize) as i64 - jitter
+        } else {
+            0
+        };
+        Duration::from_millis((self.profile.latency_ms as i64 + offset).max(0) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(latency_ms: u64, jitter_ms: u64, bandwidth_kbps: Option<u64>) -> NetworkProfile {
+        NetworkProfile {
+            latency_ms,
+            jitter_ms,
+            bandwidth_kbps,
+            loss_percent: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_latency_and_bandwidth() {
+        let mut shaper = Shaper::new(profile(50, 0, Some(8)), XorShift::new(1));
+        let now = Instant::now();
+        // 1000 bytes take a second on 8 kbit/s
+        assert_eq!(
+            now + Duration::from_millis(1050),
+            shaper.release_at(now, 1000)
+        );
+        assert_eq!(
+            now + Duration::from_millis(2050),
+            shaper.release_at(now, 1000)
+        );
+    }
+
+    #[test]
+    fn test_jitter_keeps_order() {
+        let mut shaper = Shaper::new(profile(20, 20, None), XorShift::new(7));
+        let now = Instant::now();
+        let releases: Vec<_> = (0..100)
+            .map(|idx| shaper.release_at(now + Duration::from_millis(idx), 10))
+            .collect();
+        assert!(releases.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(releases[0] <= now + Duration::from_millis(40));
+    }
+}
@@ -0,0 +1,245 @@
+use crate::benchmark_results::ExecutionReport;
+use crate::significance::{bootstrap_mean_difference, ConfidenceInterval};
+use crate::statistics::{mean, Statistics};
+use crate::summary::durations_by_suite;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RunComparison {
+    pub threshold: f64,
+    pub suites: Vec<SuiteChange>,
+    // Baseline suites without valid candidate iterations, the candidate cannot vouch for them
+    pub missing: Vec<String>,
+    // Suites new in the candidate, nothing to compare them with
+    pub added: Vec<String>,
+}
+
+// Changes are relative to the baseline mean, positive means the candidate is slower
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SuiteChange {
+    pub name: String,
+    pub baseline: Statistics,
+    pub candidate: Statistics,
+    pub relative_change: f64,
+    pub confidence_interval: Option<ConfidenceInterval>,
+    pub regressed: bool,
+}
+
+impl SuiteChange {
+    fn new(
+        name: &str,
+        baseline: &[f64],
+        candidate: &[f64],
+        threshold: f64,
+        resamples: u32,
+        level: f64,
+    ) -> Option<Self> {
+        let baseline_mean = mean(baseline);
+        let relative_change = (mean(candidate) - baseline_mean) / baseline_mean;
+        let confidence_interval = bootstrap_mean_difference(candidate, baseline, resamples, level)
+            .map(|ci| ConfidenceInterval {
+                level: ci.level,
+                low: ci.low / baseline_mean,
+                high: ci.high / baseline_mean,
+            });
+        // A slowdown only counts when the interval rules out noise as well
+        let regressed = relative_change > threshold
+            && confidence_interval
+                .as_ref()
+                .map(|ci| ci.low > 0.0)
+                .unwrap_or(true);
+        Some(SuiteChange {
+            name: name.to_owned(),
+            baseline: Statistics::new(baseline)?,
+            candidate: Statistics::new(candidate)?,
+            relative_change,
+            confidence_interval,
+            regressed,
+        })
+    }
+}
+
+impl RunComparison {
+    pub fn new(
+        baseline: &[ExecutionReport],
+        candidate: &[ExecutionReport],
+        threshold: f64,
+        resamples: u32,
+        level: f64,
+    ) -> Self {
+        let baseline = durations_by_suite(baseline);
+        let candidate = durations_by_suite(candidate);
+        let mut suites = vec![];
+        let mut missing = vec![];
+        for (name, values) in &baseline {
+            let change = candidate.get(name).and_then(|candidate| {
+                SuiteChange::new(name, values, candidate, threshold, resamples, level)
+            });
+            match change {
+                Some(change) => suites.push(change),
+                None => missing.push((*name).to_owned()),
+            }
+        }
+        let added = candidate
+            .keys()
+            .filter(|name| !baseline.contains_key(*name))
+            .map(|name| (*name).to_owned())
+            .collect();
+        RunComparison {
+            threshold,
+            suites,
+            missing,
+            added,
+        }
+    }
+
+    pub fn has_regressions(&self) -> bool {
+        self.suites.iter().any(|s| s.regressed)
+    }
+
+    // A suite which stopped running is as bad as one which got slower
+    pub fn passes(&self) -> bool {
+        !self.has_regressions() && self.missing.is_empty()
+    }
+}
+
+impl fmt::Display for RunComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .suites
+            .iter()
+            .map(|s| s.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("suite".len());
+        writeln!(
+            f,
+            "{:<width$} {:>9} {:>9} {:>8} {:>20}  status",
+            "suite",
+            "baseline",
+            "candidate",
+            "change",
+            "confidence interval",
+            width = width
+        )?;
+        for suite in &self.suites {
+            let interval = suite
+                .confidence_interval
+                .as_ref()
+                .map(|ci| format!("[{:+.1}%, {:+.1}%]", ci.low * 100.0, ci.high * 100.0))
+                .unwrap_or_else(|| "n/a".to_owned());
+            writeln!(
+                f,
+                "{:<width$} {:>8.1}s {:>8.1}s {:>+7.1}% {:>20}  {}",
+                suite.name,
+                suite.baseline.mean,
+                suite.candidate.mean,
+                suite.relative_change * 100.0,
+                interval,
+                if suite.regressed { "REGRESSION" } else { "ok" },
+                width = width
+            )?;
+        }
+        for name in &self.missing {
+            writeln!(
+                f,
+                "{:<width$} not run by the candidate  MISSING",
+                name,
+                width = width
+            )?;
+        }
+        for name in &self.added {
+            writeln!(f, "{:<width$} only in the candidate", name, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark_results::RunnerExit;
+    use std::time::{Duration, Instant};
+
+    fn reports(suites: &[(&str, u64)]) -> Vec<ExecutionReport> {
+        let start = Instant::now();
+        let exit = Some(RunnerExit {
+            code: Some(0),
+            signal: None,
+        });
+        suites
+            .iter()
+            .enumerate()
+            .map(|(idx, (suite, seconds))| {
+                ExecutionReport::new(
+                    (*suite).to_owned(),
+                    idx as u32,
+                    start,
+                    start + Duration::from_secs(*seconds),
+                    None,
+                    exit,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_suite_missing_from_candidate_fails() {
+        let baseline = reports(&[("adam", 100), ("adam", 101), ("eve", 50), ("eve", 51)]);
+        let candidate = reports(&[("adam", 100), ("adam", 101), ("bob", 10)]);
+        let comparison = RunComparison::new(&baseline, &candidate, 0.05, 1000, 0.95);
+        assert!(!comparison.has_regressions());
+        assert_eq!(vec!["eve".to_owned()], comparison.missing);
+        assert_eq!(vec!["bob".to_owned()], comparison.added);
+        assert!(!comparison.passes());
+        assert!(comparison.to_string().contains("MISSING"));
+
+        let comparison = RunComparison::new(&baseline, &baseline, 0.05, 1000, 0.95);
+        assert!(comparison.passes());
+    }
+
+    #[test]
+    fn test_slowdown_beyond_threshold_is_regression() {
+        let change = SuiteChange::new(
+            "adam",
+            &[100.0, 101.0, 99.0, 100.5, 99.5],
+            &[120.0, 121.0, 119.0, 120.5, 119.5],
+            0.05,
+            1000,
+            0.95,
+        )
+        .expect("Expected change");
+        assert!((change.relative_change - 0.2).abs() < 1e-9);
+        assert!(change.regressed);
+    }
+
+    #[test]
+    fn test_slowdown_within_threshold_is_not_regression() {
+        let change = SuiteChange::new(
+            "adam",
+            &[100.0, 101.0, 99.0],
+            &[102.0, 103.0, 101.0],
+            0.05,
+            1000,
+            0.95,
+        )
+        .expect("Expected change");
+        assert!(!change.regressed);
+    }
+
+    #[test]
+    fn test_noisy_slowdown_is_not_regression() {
+        let change = SuiteChange::new(
+            "adam",
+            &[80.0, 120.0, 100.0],
+            &[90.0, 140.0, 100.0],
+            0.05,
+            1000,
+            0.95,
+        )
+        .expect("Expected change");
+        assert!(change.relative_change > 0.05);
+        assert!(!change.regressed);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::config::Comparison;
+use crate::monitoring::{
+    ProcessMonitoringReport, ProcessSample, SystemMonitoringReport, SystemSample,
+};
+use crate::report::svg::{bar_chart, box_plot, escape, line_chart, Series};
+use crate::run_directory::RunDirectory;
+use crate::summary::{percent, RunSummary};
+use std::error::Error;
+use std::time::Duration;
+
+const KB_PER_MB: f64 = 1024.0;
+const BYTES_PER_MB: f64 = 1024.0 * 1024.0;
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin-bottom:1em}\
+th,td{border:1px solid #ccc;padding:4px 8px;text-align:right}\
+th:first-child,td:first-child{text-align:left}\
+svg{display:block;margin:1em 0}";
+
+// Renders the whole run as one HTML page, charts are inline SVG so the file has no external assets
+pub fn render_report(run_directory: &RunDirectory) -> Result<String, Box<dyn Error>> {
+    let reports = run_directory.load_execution_reports()?;
+    let summary = match run_directory.load_summary()? {
+        Some(summary) => summary,
+        None => RunSummary::new(&reports, &Comparison::default()),
+    };
+    let run_id = run_directory
+        .path()
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut html = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Benchmark {id}</title>\
+         <style>{style}</style></head><body><h1>Benchmark {id}</h1>",
+        id = escape(&run_id),
+        style = STYLE
+    );
+    html.push_str(&summary_table(&summary));
+    html.push_str(&comparison_table(&summary));
+
+    let suites = run_directory.suites()?;
+    let durations: Vec<(String, Vec<f64>)> = suites
+        .iter()
+        .map(|suite| {
+            let values = reports
+                .iter()
+                .filter(|r| r.suite_name() == suite && r.is_valid())
+                .map(|r| r.duration().as_secs_f64())
+                .collect();
+            (suite.clone(), values)
+        })
+        .collect();
+    html.push_str(&box_plot("Duration", "seconds", &durations));
+
+    for suite in &suites {
+        html.push_str(&format!("<h2>{}</h2>", escape(suite)));
+        let bars: Vec<(String, f64)> = reports
+            .iter()
+            .filter(|r| r.suite_name() == suite)
+            .map(|r| {
+                let label = if r.is_valid() {
+                    format!("#{}", r.iteration())
+                } else {
+                    format!("#{} (invalid)", r.iteration())
+                };
+                (label, r.duration().as_secs_f64())
+            })
+            .collect();
+        html.push_str(&bar_chart("Duration per iteration", "seconds", &bars));
+
+        let mut system = vec![];
+        let mut process = vec![];
+        for iteration in run_directory.iterations(suite)? {
+            if let Some(report) = run_directory.load_system_monitoring_report(suite, iteration)? {
+                system.push((iteration, report));
+            }
+            if let Some(report) = run_directory.load_process_monitoring_report(suite, iteration)? {
+                process.push((iteration, report));
+            }
+        }
+        html.push_str(&system_charts(&system));
+        html.push_str(&process_charts(&process));
+    }
+    html.push_str("</body></html>");
+    Ok(html)
+}
+
+fn summary_table(summary: &RunSummary) -> String {
+    let mut html = String::from(
+        "<table><tr><th>suite</th><th>n</th><th>min</th><th>max</th><th>mean</th>\
+         <th>median</th><th>std dev</th><th>p90</th><th>p95</th><th>cv</th></tr>",
+    );
+    for suite in &summary.suites {
+        let d = &suite.duration;
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{:.1}s</td><td>{:.1}s</td><td>{:.1}s</td>\
+             <td>{:.1}s</td><td>{:.1}s</td><td>{:.1}s</td><td>{:.1}s</td><td>{:.1}%</td></tr>",
+            escape(&suite.name),
+            d.samples,
+            d.min,
+            d.max,
+            d.mean,
+            d.median,
+            d.std_dev,
+            d.p90,
+            d.p95,
+            d.cv * 100.0
+        ));
+    }
+    html.push_str("</table>");
+    html
+}
+
+fn comparison_table(summary: &RunSummary) -> String {
+    if summary.comparisons.is_empty() {
+        return String::new();
+    }
+    let mut html = String::from(
+        "<table><tr><th>comparison</th><th>difference</th><th>confidence interval</th>\
+         <th>Cohen's d</th><th>Mann-Whitney p</th><th>Welch p</th><th>significant</th></tr>",
+    );
+    for c in &summary.comparisons {
+        let interval = c
+            .confidence_interval
+            .as_ref()
+            .map(|ci| {
+                format!(
+                    "{:.0}% [{:+.1}s, {:+.1}s]",
+                    ci.level * 100.0,
+                    ci.low,
+                    ci.high
+                )
+            })
+            .unwrap_or_else(|| "n/a".to_owned());
+        let p_value = |p: Option<f64>| {
+            p.map(|p| format!("{:.4}", p))
+                .unwrap_or_else(|| "n/a".to_owned())
+        };
+        html.push_str(&format!(
+            "<tr><td>{} vs {}</td><td>{:+.1}s ({})</td><td>{}</td><td>{:.2}</td>\
+             <td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&c.candidate),
+            escape(&c.baseline),
+            c.mean_difference,
+            percent(c.relative_difference),
+            interval,
+            c.cohens_d,
+            p_value(c.mann_whitney.as_ref().map(|t| t.p_value)),
+            p_value(c.welch.as_ref().map(|t| t.p_value)),
+            if c.significant { "yes" } else { "no" }
+        ));
+    }
+    html.push_str("</table>");
+    html
+}
+
+fn system_charts(reports: &[(u32, SystemMonitoringReport)]) -> String {
+    if reports.is_empty() {
+        return String::new();
+    }
+    let series = |value: fn(&SystemSample) -> f64| -> Vec<Series> {
+        reports
+            .iter()
+            .map(|(iteration, report)| Series {
+                label: format!("#{}", iteration),
+                points: report
+                    .samples
+                    .iter()
+                    .map(|s| (seconds(s.elapsed), value(s)))
+                    .collect(),
+            })
+            .collect()
+    };
+    [
+        line_chart(
+            "Host CPU",
+            "seconds",
+            "% (average of cores)",
+            &series(|s| {
+                s.cpu_per_core.iter().map(|c| f64::from(*c)).sum::<f64>()
+                    / s.cpu_per_core.len().max(1) as f64
+            }),
+        ),
+        line_chart(
+            "Host memory",
+            "seconds",
+            "used MB",
+            &series(|s| s.memory_used_kb as f64 / KB_PER_MB),
+        ),
+        line_chart(
+            "Host disk IO",
+            "seconds",
+            "read + written MB/s",
+            &series(|s| {
+                (s.disk_read_bytes_per_sec + s.disk_written_bytes_per_sec) as f64 / BYTES_PER_MB
+            }),
+        ),
+        line_chart(
+            "Host network",
+            "seconds",
+            "received + transmitted MB/s",
+            &series(|s| {
+                (s.net_received_bytes_per_sec + s.net_transmitted_bytes_per_sec) as f64
+                    / BYTES_PER_MB
+            }),
+        ),
+    ]
+    .concat()
+}
+
+fn process_charts(reports: &[(u32, ProcessMonitoringReport)]) -> String {
+    if reports.is_empty() {
+        return String::new();
+    }
+    let series = |value: fn(&ProcessSample) -> f64| -> Vec<Series> {
+        reports
+            .iter()
+            .map(|(iteration, report)| Series {
+                label: format!("#{}", iteration),
+                points: report
+                    .samples
+                    .iter()
+                    .map(|s| (seconds(s.elapsed), value(s)))
+                    .collect(),
+            })
+            .collect()
+    };
+    [
+        line_chart(
+            "Runner CPU",
+            "seconds",
+            "% of one core",
+            &series(|s| s.cpu_usage),
+        ),
+        line_chart(
+            "Runner memory",
+            "seconds",
+            "RSS MB",
+            &series(|s| s.rss_kb as f64 / KB_PER_MB),
+        ),
+        line_chart(
+            "Runner threads",
+            "seconds",
+            "threads",
+            &series(|s| s.threads as f64),
+        ),
+    ]
+    .concat()
+}
+
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs_f64()
+}
@@ -0,0 +1,4 @@
+mod html;
+mod svg;
+
+pub use html::render_report;
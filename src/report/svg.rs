@@ -0,0 +1,267 @@
+use crate::statistics::percentile;
+
+const WIDTH: f64 = 720.0;
+const HEIGHT: f64 = 260.0;
+const MARGIN_LEFT: f64 = 64.0;
+const MARGIN_RIGHT: f64 = 16.0;
+const MARGIN_TOP: f64 = 32.0;
+const MARGIN_BOTTOM: f64 = 40.0;
+const Y_TICKS: u32 = 5;
+const PALETTE: [&str; 8] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#9c755f",
+];
+
+pub struct Series {
+    pub label: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+pub fn line_chart(title: &str, x_label: &str, y_label: &str, series: &[Series]) -> String {
+    let points = series.iter().flat_map(|s| s.points.iter());
+    let x_max = nice_max(points.clone().map(|p| p.0).fold(0.0, f64::max));
+    let y_max = nice_max(points.map(|p| p.1).fold(0.0, f64::max));
+    let mut body = String::new();
+    for (idx, s) in series.iter().enumerate() {
+        let color = PALETTE[idx % PALETTE.len()];
+        let coordinates: Vec<String> = s
+            .points
+            .iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", scale_x(*x, x_max), scale_y(*y, y_max)))
+            .collect();
+        body.push_str(&format!(
+            r#"<polyline fill="none" stroke="{}" stroke-width="1.5" points="{}"/>"#,
+            color,
+            coordinates.join(" ")
+        ));
+        body.push_str(&legend(idx, &s.label, color));
+    }
+    for tick in 0..=Y_TICKS {
+        let x = x_max * f64::from(tick) / f64::from(Y_TICKS);
+        body.push_str(&format!(
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+            scale_x(x, x_max),
+            HEIGHT - MARGIN_BOTTOM + 14.0,
+            format_value(x)
+        ));
+    }
+    frame(title, x_label, y_label, y_max, &body)
+}
+
+pub fn bar_chart(title: &str, y_label: &str, bars: &[(String, f64)]) -> String {
+    let y_max = nice_max(bars.iter().map(|b| b.1).fold(0.0, f64::max));
+    let slot = plot_width() / bars.len().max(1) as f64;
+    let mut body = String::new();
+    for (idx, (label, value)) in bars.iter().enumerate() {
+        let x = MARGIN_LEFT + slot * idx as f64;
+        let y = scale_y(*value, y_max);
+        body.push_str(&format!(
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"><title>{}: {}</title></rect>"#,
+            x + slot * 0.15,
+            y,
+            slot * 0.7,
+            HEIGHT - MARGIN_BOTTOM - y,
+            PALETTE[0],
+            escape(label),
+            format_value(*value)
+        ));
+        body.push_str(&category_label(x + slot / 2.0, label));
+    }
+    frame(title, "", y_label, y_max, &body)
+}
+
+// Whiskers span min to max, the box spans the interquartile range
+pub fn box_plot(title: &str, y_label: &str, boxes: &[(String, Vec<f64>)]) -> String {
+    let y_max = nice_max(
+        boxes
+            .iter()
+            .flat_map(|b| b.1.iter().copied())
+            .fold(0.0, f64::max),
+    );
+    let slot = plot_width() / boxes.len().max(1) as f64;
+    let mut body = String::new();
+    for (idx, (label, values)) in boxes.iter().enumerate() {
+        let center = MARGIN_LEFT + slot * (idx as f64 + 0.5);
+        body.push_str(&category_label(center, label));
+        if values.is_empty() {
+            continue;
+        }
+        let mut sorted = values.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).expect("Cannot compare NaN"));
+        let y = |percent: f64| scale_y(percentile(&sorted, percent), y_max);
+        let half = (slot * 0.3).min(40.0);
+        let color = PALETTE[idx % PALETTE.len()];
+        body.push_str(&format!(
+            r#"<line x1="{c:.1}" y1="{min:.1}" x2="{c:.1}" y2="{max:.1}" stroke="{color}"/>"#,
+            c = center,
+            min = y(0.0),
+            max = y(100.0),
+            color = color
+        ));
+        for percent in &[0.0, 100.0] {
+            body.push_str(&format!(
+                r#"<line x1="{:.1}" y1="{y:.1}" x2="{:.1}" y2="{y:.1}" stroke="{}"/>"#,
+                center - half / 2.0,
+                center + half / 2.0,
+                color,
+                y = y(*percent)
+            ));
+        }
+        body.push_str(&format!(
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}" fill-opacity="0.4" stroke="{}"/>"#,
+            center - half,
+            y(75.0),
+            half * 2.0,
+            y(25.0) - y(75.0),
+            color,
+            color
+        ));
+        body.push_str(&format!(
+            r#"<line x1="{:.1}" y1="{y:.1}" x2="{:.1}" y2="{y:.1}" stroke="{}" stroke-width="2"/>"#,
+            center - half,
+            center + half,
+            color,
+            y = y(50.0)
+        ));
+        for value in values {
+            body.push_str(&format!(
+                r#"<circle cx="{:.1}" cy="{:.1}" r="2.5" fill="{}"/>"#,
+                center,
+                scale_y(*value, y_max),
+                color
+            ));
+        }
+    }
+    frame(title, "", y_label, y_max, &body)
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn frame(title: &str, x_label: &str, y_label: &str, y_max: f64, body: &str) -> String {
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {w} {h}" width="{w}" height="{h}" font-family="sans-serif" font-size="11">"#,
+        w = WIDTH,
+        h = HEIGHT
+    );
+    svg.push_str(&format!(
+        r#"<text x="{:.1}" y="18" font-size="13" font-weight="bold">{}</text>"#,
+        MARGIN_LEFT,
+        escape(title)
+    ));
+    for tick in 0..=Y_TICKS {
+        let value = y_max * f64::from(tick) / f64::from(Y_TICKS);
+        let y = scale_y(value, y_max);
+        svg.push_str(&format!(
+            r##"<line x1="{:.1}" y1="{y:.1}" x2="{:.1}" y2="{y:.1}" stroke="#e0e0e0"/><text x="{:.1}" y="{:.1}" text-anchor="end">{}</text>"##,
+            MARGIN_LEFT,
+            WIDTH - MARGIN_RIGHT,
+            MARGIN_LEFT - 6.0,
+            y + 4.0,
+            format_value(value),
+            y = y
+        ));
+    }
+    svg.push_str(&format!(
+        r#"<line x1="{l:.1}" y1="{t:.1}" x2="{l:.1}" y2="{b:.1}" stroke="black"/><line x1="{l:.1}" y1="{b:.1}" x2="{r:.1}" y2="{b:.1}" stroke="black"/>"#,
+        l = MARGIN_LEFT,
+        r = WIDTH - MARGIN_RIGHT,
+        t = MARGIN_TOP,
+        b = HEIGHT - MARGIN_BOTTOM
+    ));
+    svg.push_str(&format!(
+        r#"<text transform="translate(14 {:.1}) rotate(-90)" text-anchor="middle">{}</text>"#,
+        MARGIN_TOP + plot_height() / 2.0,
+        escape(y_label)
+    ));
+    svg.push_str(&format!(
+        r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+        MARGIN_LEFT + plot_width() / 2.0,
+        HEIGHT - 6.0,
+        escape(x_label)
+    ));
+    svg.push_str(body);
+    svg.push_str("</svg>");
+    svg
+}
+
+fn legend(idx: usize, label: &str, color: &str) -> String {
+    let y = MARGIN_TOP + 6.0 + 14.0 * idx as f64;
+    format!(
+        r#"<rect x="{:.1}" y="{:.1}" width="10" height="10" fill="{}"/><text x="{:.1}" y="{:.1}">{}</text>"#,
+        WIDTH - MARGIN_RIGHT - 110.0,
+        y,
+        color,
+        WIDTH - MARGIN_RIGHT - 96.0,
+        y + 9.0,
+        escape(label)
+    )
+}
+
+fn category_label(x: f64, label: &str) -> String {
+    format!(
+        r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+        x,
+        HEIGHT - MARGIN_BOTTOM + 14.0,
+        escape(label)
+    )
+}
+
+fn plot_width() -> f64 {
+    WIDTH - MARGIN_LEFT - MARGIN_RIGHT
+}
+
+fn plot_height() -> f64 {
+    HEIGHT - MARGIN_TOP - MARGIN_BOTTOM
+}
+
+fn scale_x(value: f64, max: f64) -> f64 {
+    MARGIN_LEFT + value / max * plot_width()
+}
+
+fn scale_y(value: f64, max: f64) -> f64 {
+    HEIGHT - MARGIN_BOTTOM - value / max * plot_height()
+}
+
+fn format_value(value: f64) -> String {
+    if value >= 100.0 || value.fract() == 0.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.1}", value)
+    }
+}
+
+// Rounds the axis maximum up to 1, 2 or 5 times a power of ten
+fn nice_max(value: f64) -> f64 {
+    if value <= 0.0 {
+        return 1.0;
+    }
+    let magnitude = 10f64.powf(value.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|step| step * magnitude)
+        .find(|max| *max >= value)
+        .unwrap_or(10.0 * magnitude)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nice_max() {
+        assert_eq!(1.0, nice_max(0.0));
+        assert_eq!(1.0, nice_max(0.8));
+        assert_eq!(200.0, nice_max(123.0));
+        assert_eq!(500.0, nice_max(500.0));
+        assert_eq!(1000.0, nice_max(501.0));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!("a &lt;b&gt; &amp; &quot;c&quot;", escape("a <b> & \"c\""))
+    }
+}
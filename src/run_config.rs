@@ -1,19 +1,19 @@
 use std::path::PathBuf;
-use std::time::Instant;
+use std::time::SystemTime;
 use uuid::Uuid;
 
 pub struct RunConfig {
     pub uuid: Uuid,
     pub working_dir: PathBuf,
-    start: Instant,
+    pub started_at: SystemTime,
 }
 
 impl RunConfig {
-    pub fn new(uuid: Uuid, working_dir: PathBuf, start: Instant) -> Self {
+    pub fn new(uuid: Uuid, working_dir: PathBuf) -> Self {
         RunConfig {
             uuid,
             working_dir,
-            start,
+            started_at: SystemTime::now(),
         }
     }
 }
@@ -0,0 +1,116 @@
+use crate::benchmark_results::ExecutionReport;
+use crate::monitoring::{ProcessMonitoringReport, SystemMonitoringReport};
+use crate::summary::RunSummary;
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+// Read side of the `<working_dir>/<uuid>/<suite>/<iteration>` layout written by FileManager
+pub struct RunDirectory {
+    path: PathBuf,
+}
+
+impl RunDirectory {
+    pub fn new(working_dir: &Path, uuid: Uuid) -> Self {
+        RunDirectory {
+            path: working_dir.join(uuid.to_string()),
+        }
+    }
+
+    pub fn from_path(path: PathBuf) -> Self {
+        RunDirectory { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn suites(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut suites = vec![];
+        for entry in std::fs::read_dir(&self.path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                suites.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        suites.sort();
+        Ok(suites)
+    }
+
+    pub fn iterations(&self, suite: &str) -> Result<Vec<u32>, Box<dyn Error>> {
+        let mut iterations = vec![];
+        for entry in std::fs::read_dir(self.path.join(suite))? {
+            let entry = entry?;
+            let iteration = entry.file_name().to_str().and_then(|n| n.parse().ok());
+            if let (true, Some(iteration)) = (entry.file_type()?.is_dir(), iteration) {
+                iterations.push(iteration);
+            }
+        }
+        iterations.sort_unstable();
+        Ok(iterations)
+    }
+
+    pub fn iteration_dir(&self, suite: &str, iteration: u32) -> PathBuf {
+        self.path.join(suite).join(iteration.to_string())
+    }
+
+    pub fn load_execution_reports(&self) -> Result<Vec<ExecutionReport>, Box<dyn Error>> {
+        let mut reports = vec![];
+        for suite in self.suites()? {
+            for iteration in self.iterations(&suite)? {
+                let path = self
+                    .iteration_dir(&suite, iteration)
+                    .join("execution_report.json");
+                // Iterations which failed before the runner finished have no report
+                if let Some(report) = load_json(&path)? {
+                    reports.push(report);
+                }
+            }
+        }
+        Ok(reports)
+    }
+
+    pub fn load_system_monitoring_report(
+        &self,
+        suite: &str,
+        iteration: u32,
+    ) -> Result<Option<SystemMonitoringReport>, Box<dyn Error>> {
+        load_json(
+            &self
+                .iteration_dir(suite, iteration)
+                .join("system_monitoring.json"),
+        )
+    }
+
+    pub fn load_process_monitoring_report(
+        &self,
+        suite: &str,
+        iteration: u32,
+    ) -> Result<Option<ProcessMonitoringReport>, Box<dyn Error>> {
+        load_json(
+            &self
+                .iteration_dir(suite, iteration)
+                .join("results")
+                .join("process_monitoring.json"),
+        )
+    }
+
+    pub fn load_summary(&self) -> Result<Option<RunSummary>, Box<dyn Error>> {
+        load_json(&self.path.join("summary.json"))
+    }
+
+    pub fn save_summary(&self, summary: &RunSummary) -> Result<(), Box<dyn Error>> {
+        let summary = serde_json::to_string_pretty(summary)?;
+        std::fs::write(self.path.join("summary.json"), summary)?;
+        Ok(())
+    }
+}
+
+fn load_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(path)?;
+    Ok(Some(serde_json::from_str(&content)?))
+}
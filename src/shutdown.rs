@@ -0,0 +1,59 @@
+use std::io::{Error, ErrorKind};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+
+// Counts SIGINT/SIGTERM, the first one asks for a graceful teardown, the second forces it
+#[derive(Clone)]
+pub struct Shutdown {
+    signals: watch::Receiver<u32>,
+}
+
+impl Shutdown {
+    pub fn listen() -> Self {
+        let (sender, signals) = watch::channel(0);
+        tokio::spawn(async move {
+            let mut terminate = signal(SignalKind::terminate()).expect("Cannot listen for SIGTERM");
+            let mut count = 0;
+            loop {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {},
+                    _ = terminate.recv() => {},
+                }
+                count += 1;
+                if count == 1 {
+                    eprintln!("Interrupted, terminating devices. Press Ctrl-C again to force it");
+                }
+                if sender.send(count).is_err() {
+                    break;
+                }
+            }
+        });
+        Shutdown { signals }
+    }
+
+    pub async fn requested(&self) {
+        self.wait_for(1).await
+    }
+
+    pub async fn forced(&self) {
+        self.wait_for(2).await
+    }
+
+    pub fn is_forced(&self) -> bool {
+        *self.signals.borrow() >= 2
+    }
+
+    async fn wait_for(&self, count: u32) {
+        let mut signals = self.signals.clone();
+        while *signals.borrow() < count {
+            if signals.changed().await.is_err() {
+                // Nobody can send signals anymore
+                std::future::pending::<()>().await;
+            }
+        }
+    }
+}
+
+pub fn interrupted() -> Box<dyn std::error::Error> {
+    Box::new(Error::new(ErrorKind::Interrupted, "Interrupted by signal"))
+}
@@ -0,0 +1,310 @@
+use crate::random::XorShift;
+use crate::statistics::{mean, percentile, variance};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+// Samples small enough to enumerate the exact distribution of U
+const MANN_WHITNEY_EXACT_LIMIT: usize = 400;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignificanceTest {
+    pub statistic: f64,
+    pub p_value: f64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConfidenceInterval {
+    pub level: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+// Two-sided Mann-Whitney U test, exact for small samples without ties
+pub fn mann_whitney_u(a: &[f64], b: &[f64]) -> Option<SignificanceTest> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let (n1, n2) = (a.len(), b.len());
+    let n = (n1 + n2) as f64;
+    let (ranks, ties) = rank(a.iter().chain(b.iter()).copied().collect());
+    let r1: f64 = ranks[..n1].iter().sum();
+    let u1 = r1 - (n1 * (n1 + 1)) as f64 / 2.0;
+    let u2 = (n1 * n2) as f64 - u1;
+
+    let p_value = if ties == 0.0 && n1 * n2 <= MANN_WHITNEY_EXACT_LIMIT {
+        let counts = u_distribution(n1, n2);
+        let total: f64 = counts.iter().sum();
+        let u = u1.min(u2) as usize;
+        (2.0 * counts[..=u].iter().sum::<f64>() / total).min(1.0)
+    } else {
+        let mean = (n1 * n2) as f64 / 2.0;
+        let variance = (n1 * n2) as f64 / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)));
+        if variance <= 0.0 {
+            1.0
+        } else {
+            let z = ((u1 - mean).abs() - 0.5).max(0.0) / variance.sqrt();
+            erfc(z / 2f64.sqrt()).min(1.0)
+        }
+    };
+    Some(SignificanceTest {
+        statistic: u1,
+        p_value,
+    })
+}
+
+// Two-sided t-test without the equal variances assumption
+pub fn welch_t_test(a: &[f64], b: &[f64]) -> Option<SignificanceTest> {
+    if a.len() < 2 || b.len() < 2 {
+        return None;
+    }
+    let (na, nb) = (a.len() as f64, b.len() as f64);
+    let (va, vb) = (variance(a) / na, variance(b) / nb);
+    let difference = mean(a) - mean(b);
+    let standard_error = (va + vb).sqrt();
+    if standard_error == 0.0 {
+        let p_value = if difference == 0.0 { 1.0 } else { 0.0 };
+        return Some(SignificanceTest {
+            statistic: 0.0,
+            p_value,
+        });
+    }
+    let t = difference / standard_error;
+    let df = (va + vb).powi(2) / (va.powi(2) / (na - 1.0) + vb.powi(2) / (nb - 1.0));
+    Some(SignificanceTest {
+        statistic: t,
+        p_value: incomplete_beta(df / 2.0, 0.5, df / (df + t * t)),
+    })
+}
+
+// Difference of means in units of the pooled standard deviation
+pub fn cohens_d(a: &[f64], b: &[f64]) -> f64 {
+    let (na, nb) = (a.len() as f64, b.len() as f64);
+    if na + nb <= 2.0 {
+        return 0.0;
+    }
+    let pooled = (((na - 1.0) * variance(a) + (nb - 1.0) * variance(b)) / (na + nb - 2.0)).sqrt();
+    if pooled == 0.0 {
+        0.0
+    } else {
+        (mean(a) - mean(b)) / pooled
+    }
+}
+
+// Percentile bootstrap of `mean(a) - mean(b)`, seeded so reruns give the same interval
+pub fn bootstrap_mean_difference(
+    a: &[f64],
+    b: &[f64],
+    resamples: u32,
+    level: f64,
+) -> Option<ConfidenceInterval> {
+    if a.is_empty() || b.is_empty() || resamples == 0 {
+        return None;
+    }
+    let mut rng = XorShift::new(0x9E37_79B9_7F4A_7C15);
+    let mut resample_mean = |values: &[f64]| {
+        (0..values.len())
+            .map(|_| values[rng.below(values.len())])
+            .sum::<f64>()
+            / values.len() as f64
+    };
+    let mut differences: Vec<f64> = (0..resamples)
+        .map(|_| resample_mean(a) - resample_mean(b))
+        .collect();
+    differences.sort_by(|x, y| x.partial_cmp(y).expect("Cannot compare NaN"));
+    let tail = (1.0 - level) / 2.0 * 100.0;
+    Some(ConfidenceInterval {
+        level,
+        low: percentile(&differences, tail),
+        high: percentile(&differences, 100.0 - tail),
+    })
+}
+
+// Average ranks (1-based) and the tie correction term sum(t^3 - t)
+fn rank(values: Vec<f64>) -> (Vec<f64>, f64) {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&x, &y| {
+        values[x]
+            .partial_cmp(&values[y])
+            .expect("Cannot compare NaN")
+    });
+    let mut ranks = vec![0.0; values.len()];
+    let mut ties = 0.0;
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start;
+        while end + 1 < order.len() && values[order[end + 1]] == values[order[start]] {
+            end += 1;
+        }
+        let average = (start + end) as f64 / 2.0 + 1.0;
+        for &idx in &order[start..=end] {
+            ranks[idx] = average;
+        }
+        let t = (end - start + 1) as f64;
+        ties += t.powi(3) - t;
+        start = end + 1;
+    }
+    (ranks, ties)
+}
+
+// Number of arrangements producing each value of U for samples of size n1 and n2
+fn u_distribution(n1: usize, n2: usize) -> Vec<f64> {
+    // counts[j] holds the distribution for sizes (i, j) while iterating over i
+    let mut counts: Vec<Vec<f64>> = (0..=n2).map(|_| vec![1.0]).collect();
+    for i in 1..=n1 {
+        let mut next: Vec<Vec<f64>> = vec![vec![1.0]];
+        for j in 1..=n2 {
+            let mut distribution = vec![0.0; i * j + 1];
+            for (u, count) in next[j - 1].iter().enumerate() {
+                distribution[u] += count;
+            }
+            for (u, count) in counts[j].iter().enumerate() {
+                distribution[u + j] += count;
+            }
+            next.push(distribution);
+        }
+        counts = next;
+    }
+    counts.pop().expect("Expected distribution")
+}
+
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t
+        * (-z * z - 1.265_512_23
+            + t * (1.000_023_68
+                + t * (0.374_091_96
+                    + t * (0.096_784_18
+                        + t * (-0.186_288_06
+                            + t * (0.278_868_07
+                                + t * (-1.135_203_98
+                                    + t * (1.488_515_87
+                                        + t * (-0.822_152_23 + t * 0.170_872_77)))))))))
+            .exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .skip(1)
+        .fold(COEFFICIENTS[0], |acc, (i, c)| acc + c / (x + i as f64));
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+// Regularized incomplete beta function I_x(a, b)
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const MAX_ITERATIONS: u32 = 300;
+    const EPSILON: f64 = 1e-15;
+    const TINY: f64 = 1e-300;
+    let non_zero = |v: f64| if v.abs() < TINY { TINY } else { v };
+
+    let mut c = 1.0;
+    let mut d = 1.0 / non_zero(1.0 - (a + b) * x / (a + 1.0));
+    let mut h = d;
+    for m in 1..=MAX_ITERATIONS {
+        let m = m as f64;
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / non_zero(1.0 + even * d);
+        c = non_zero(1.0 + even / c);
+        h *= d * c;
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / non_zero(1.0 + odd * d);
+        c = non_zero(1.0 + odd / c);
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(expected: f64, actual: f64, tolerance: f64) {
+        assert!(
+            (expected - actual).abs() < tolerance,
+            "expected {} but was {}",
+            expected,
+            actual
+        )
+    }
+
+    #[test]
+    fn test_welch_t_test() {
+        let a = [
+            27.5, 21.0, 19.0, 23.6, 17.0, 17.9, 16.9, 20.1, 21.9, 22.6, 23.1, 19.6, 19.0, 21.7,
+            21.4,
+        ];
+        let b = [
+            27.1, 22.0, 20.8, 23.4, 23.4, 23.5, 25.8, 22.0, 24.8, 20.2, 21.9, 22.1, 22.9, 20.5,
+            24.4,
+        ];
+        let result = welch_t_test(&a, &b).expect("Expected test result");
+        assert_close(-2.46, result.statistic, 0.01);
+        assert_close(0.021, result.p_value, 0.001);
+    }
+
+    #[test]
+    fn test_mann_whitney_u_exact() {
+        let result = mann_whitney_u(&[1.0, 2.0, 3.0, 4.0, 6.0], &[5.0, 7.0, 8.0, 9.0, 10.0])
+            .expect("Expected test result");
+        assert_close(1.0, result.statistic, 1e-9);
+        assert_close(4.0 / 252.0, result.p_value, 1e-9);
+    }
+
+    #[test]
+    fn test_mann_whitney_u_identical_samples() {
+        let result = mann_whitney_u(&[1.0, 1.0, 1.0], &[1.0, 1.0]).expect("Expected test result");
+        assert_close(1.0, result.p_value, 1e-9);
+    }
+
+    #[test]
+    fn test_bootstrap_mean_difference() {
+        let a = [10.0, 11.0, 12.0, 10.5, 11.5];
+        let b = [20.0, 21.0, 22.0, 20.5, 21.5];
+        let interval = bootstrap_mean_difference(&a, &b, 2000, 0.95).expect("Expected interval");
+        assert!(interval.low < -10.0 && -10.0 < interval.high);
+        assert!(interval.high < 0.0);
+    }
+}
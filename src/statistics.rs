@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Statistics {
+    pub samples: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub std_dev: f64,
+    pub p90: f64,
+    pub p95: f64,
+    // Standard deviation relative to the mean
+    pub cv: f64,
+}
+
+impl Statistics {
+    pub fn new(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).expect("Cannot compare NaN"));
+        let mean = mean(&sorted);
+        let std_dev = std_dev(&sorted);
+        Some(Statistics {
+            samples: sorted.len(),
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean,
+            median: percentile(&sorted, 50.0),
+            std_dev,
+            p90: percentile(&sorted, 90.0),
+            p95: percentile(&sorted, 95.0),
+            cv: if mean != 0.0 { std_dev / mean } else { 0.0 },
+        })
+    }
+}
+
+pub fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+// Sample variance, 0 when there is not enough data to estimate it
+pub fn variance(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = mean(values);
+    values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64
+}
+
+pub fn std_dev(values: &[f64]) -> f64 {
+    variance(values).sqrt()
+}
+
+// Linear interpolation between closest ranks, `sorted` must be in ascending order.
+// Percents outside 0..=100 give the extremes rather than reading past the end
+pub fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    let rank = percent.clamp(0.0, 100.0) / 100.0 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(expected: f64, actual: f64) {
+        assert!(
+            (expected - actual).abs() < 1e-9,
+            "expected {} but was {}",
+            expected,
+            actual
+        )
+    }
+
+    #[test]
+    fn test_statistics() {
+        let stats = Statistics::new(&[4.0, 1.0, 3.0, 2.0, 5.0]).expect("Expected statistics");
+        assert_eq!(5, stats.samples);
+        assert_close(1.0, stats.min);
+        assert_close(5.0, stats.max);
+        assert_close(3.0, stats.mean);
+        assert_close(3.0, stats.median);
+        assert_close(2.5f64.sqrt(), stats.std_dev);
+        assert_close(4.6, stats.p90);
+        assert_close(4.8, stats.p95);
+        assert_close(2.5f64.sqrt() / 3.0, stats.cv);
+    }
+
+    #[test]
+    fn test_statistics_single_value() {
+        let stats = Statistics::new(&[7.0]).expect("Expected statistics");
+        assert_close(7.0, stats.median);
+        assert_close(7.0, stats.p95);
+        assert_close(0.0, stats.std_dev);
+    }
+
+    #[test]
+    fn test_statistics_empty() {
+        assert_eq!(None, Statistics::new(&[]))
+    }
+
+    #[test]
+    fn test_percentile_out_of_range() {
+        let sorted = [1.0, 2.0, 3.0];
+        assert_close(1.0, percentile(&sorted, -5.0));
+        assert_close(3.0, percentile(&sorted, 150.0));
+    }
+}
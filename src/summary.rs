@@ -0,0 +1,472 @@
+use crate::benchmark_results::ExecutionReport;
+use crate::config::Comparison;
+use crate::devices::Disruption;
+use crate::significance::{
+    bootstrap_mean_difference, cohens_d, mann_whitney_u, welch_t_test, ConfidenceInterval,
+    SignificanceTest,
+};
+use crate::statistics::{mean, Statistics};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub suites: Vec<SuiteSummary>,
+    pub comparisons: Vec<SuiteComparison>,
+    #[serde(default)]
+    pub chaos: Vec<ChaosSummary>,
+}
+
+// Durations are in seconds
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SuiteSummary {
+    pub name: String,
+    pub duration: Statistics,
+}
+
+// Differences are `candidate - baseline` in seconds, negative means the candidate is faster
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SuiteComparison {
+    pub baseline: String,
+    pub candidate: String,
+    pub mean_difference: f64,
+    // None when the baseline mean is zero
+    pub relative_difference: Option<f64>,
+    pub confidence_interval: Option<ConfidenceInterval>,
+    pub cohens_d: f64,
+    pub mann_whitney: Option<SignificanceTest>,
+    pub welch: Option<SignificanceTest>,
+    pub alpha: f64,
+    // Both tests have to reject the null hypothesis
+    pub significant: bool,
+}
+
+// How a runner coped with chaos mode, averages are per iteration
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChaosSummary {
+    pub suite: String,
+    pub baseline: Option<String>,
+    pub iterations: u32,
+    // The runner still finished successfully
+    pub recovered: u32,
+    pub disruptions: f64,
+    pub rerun_tests: f64,
+    // Tests the baseline reported which the iteration did not
+    pub lost_tests: Option<f64>,
+    // Seconds recovered iterations took on top of the baseline mean
+    pub extra_time: Option<f64>,
+}
+
+impl ChaosSummary {
+    fn new(suite: &str, reports: &[&ExecutionReport], all: &[ExecutionReport]) -> Self {
+        let baseline = reports
+            .iter()
+            .find_map(|report| report.chaos().and_then(|chaos| chaos.baseline.clone()));
+        let baseline_reports: Vec<&ExecutionReport> = all
+            .iter()
+            .filter(|report| Some(report.suite_name()) == baseline.as_deref())
+            .collect();
+        let baseline_tests: HashSet<(&str, &str)> = baseline_reports
+            .iter()
+            .filter_map(|report| report.tests())
+            .flat_map(|tests| &tests.tests)
+            .map(|test| (test.class_name.as_str(), test.name.as_str()))
+            .collect();
+        let per_iteration = |value: &dyn Fn(&ExecutionReport) -> f64| {
+            mean(
+                &reports
+                    .iter()
+                    .map(|report| value(report))
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        let lost_tests = if baseline_tests.is_empty() {
+            None
+        } else {
+            Some(per_iteration(&|report| {
+                let reported: HashSet<(&str, &str)> = report
+                    .tests()
+                    .map(|tests| &tests.tests)
+                    .into_iter()
+                    .flatten()
+                    .map(|test| (test.class_name.as_str(), test.name.as_str()))
+                    .collect();
+                baseline_tests.difference(&reported).count() as f64
+            }))
+        };
+        let baseline_durations: Vec<f64> = baseline_reports
+            .iter()
+            .filter(|report| report.is_valid())
+            .map(|report| report.duration().as_secs_f64())
+            .collect();
+        let recovered_durations: Vec<f64> = reports
+            .iter()
+            .filter(|report| report.succeeded())
+            .map(|report| report.duration().as_secs_f64())
+            .collect();
+        let extra_time = if baseline_durations.is_empty() || recovered_durations.is_empty() {
+            None
+        } else {
+            Some(mean(&recovered_durations) - mean(&baseline_durations))
+        };
+
+        ChaosSummary {
+            suite: suite.to_owned(),
+            baseline,
+            iterations: reports.len() as u32,
+            recovered: recovered_durations.len() as u32,
+            // Resuming a paused device is part of the same disruption
+            disruptions: per_iteration(&|report| {
+                report.chaos().map_or(0, |chaos| {
+                    chaos
+                        .disturbances
+                        .iter()
+                        .filter(|d| d.disruption != Disruption::Resume)
+                        .count()
+                }) as f64
+            }),
+            rerun_tests: per_iteration(&|report| {
+                report.tests().map_or(0, |tests| {
+                    tests
+                        .tests
+                        .iter()
+                        .map(|test| test.attempts.saturating_sub(1))
+                        .sum()
+                }) as f64
+            }),
+            lost_tests,
+            extra_time,
+        }
+    }
+}
+
+impl SuiteComparison {
+    fn new(baseline: (&str, &[f64]), candidate: (&str, &[f64]), comparison: &Comparison) -> Self {
+        let (baseline_name, b) = baseline;
+        let (candidate_name, a) = candidate;
+        let mean_difference = mean(a) - mean(b);
+        let mann_whitney = mann_whitney_u(a, b);
+        let welch = welch_t_test(a, b);
+        let rejects = |test: &Option<SignificanceTest>| {
+            test.as_ref()
+                .map(|t| t.p_value < comparison.alpha)
+                .unwrap_or(false)
+        };
+        SuiteComparison {
+            baseline: baseline_name.to_owned(),
+            candidate: candidate_name.to_owned(),
+            mean_difference,
+            relative_difference: Some(mean_difference / mean(b)).filter(|r| r.is_finite()),
+            confidence_interval: bootstrap_mean_difference(
+                a,
+                b,
+                comparison.bootstrap_resamples,
+                1.0 - comparison.alpha,
+            ),
+            cohens_d: cohens_d(a, b),
+            significant: rejects(&mann_whitney) && rejects(&welch),
+            mann_whitney,
+            welch,
+            alpha: comparison.alpha,
+        }
+    }
+}
+
+impl RunSummary {
+    pub fn new(reports: &[ExecutionReport], comparison: &Comparison) -> Self {
+        let durations = durations_by_suite(reports);
+        let suites = durations
+            .iter()
+            .filter_map(|(name, values)| {
+                Some(SuiteSummary {
+                    name: (*name).to_owned(),
+                    duration: Statistics::new(values)?,
+                })
+            })
+            .collect();
+        let durations: Vec<(&str, &[f64])> = durations
+            .iter()
+            .map(|(name, values)| (*name, values.as_slice()))
+            .collect();
+        let mut comparisons = vec![];
+        for (idx, baseline) in durations.iter().enumerate() {
+            for candidate in &durations[idx + 1..] {
+                comparisons.push(SuiteComparison::new(*baseline, *candidate, comparison));
+            }
+        }
+        RunSummary {
+            suites,
+            comparisons,
+            chaos: chaos_by_suite(reports),
+        }
+    }
+}
+
+// Chaos iterations usually fail, so all of them count here
+fn chaos_by_suite(reports: &[ExecutionReport]) -> Vec<ChaosSummary> {
+    let mut suites: BTreeMap<&str, Vec<&ExecutionReport>> = BTreeMap::new();
+    for report in reports.iter().filter(|r| r.chaos().is_some()) {
+        suites.entry(report.suite_name()).or_default().push(report);
+    }
+    suites
+        .iter()
+        .map(|(suite, chaos_reports)| ChaosSummary::new(suite, chaos_reports, reports))
+        .collect()
+}
+
+// Durations in seconds of valid iterations grouped by suite name
+pub fn durations_by_suite(reports: &[ExecutionReport]) -> BTreeMap<&str, Vec<f64>> {
+    let mut durations: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+    for report in reports.iter().filter(|r| r.is_valid()) {
+        durations
+            .entry(report.suite_name())
+            .or_default()
+            .push(report.duration().as_secs_f64());
+    }
+    durations
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .suites
+            .iter()
+            .map(|s| s.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("suite".len());
+        writeln!(
+            f,
+            "{:<width$} {:>4} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>7}",
+            "suite",
+            "n",
+            "min",
+            "max",
+            "mean",
+            "median",
+            "std dev",
+            "p90",
+            "p95",
+            "cv",
+            width = width
+        )?;
+        for suite in &self.suites {
+            let d = &suite.duration;
+            writeln!(
+                f,
+                "{:<width$} {:>4} {:>8.1}s {:>8.1}s {:>8.1}s {:>8.1}s {:>8.1}s {:>8.1}s {:>8.1}s {:>6.1}%",
+                suite.name,
+                d.samples,
+                d.min,
+                d.max,
+                d.mean,
+                d.median,
+                d.std_dev,
+                d.p90,
+                d.p95,
+                d.cv * 100.0,
+                width = width
+            )?;
+        }
+        for c in &self.comparisons {
+            let p_value = |test: &Option<SignificanceTest>| {
+                test.as_ref()
+                    .map(|t| format!("{:.4}", t.p_value))
+                    .unwrap_or_else(|| "n/a".to_owned())
+            };
+            write!(
+                f,
+                "\n{} vs {}: {:+.1}s ({})",
+                c.candidate,
+                c.baseline,
+                c.mean_difference,
+                percent(c.relative_difference)
+            )?;
+            if let Some(ci) = &c.confidence_interval {
+                write!(
+                    f,
+                    ", {:.0}% CI [{:+.1}s, {:+.1}s]",
+                    ci.level * 100.0,
+                    ci.low,
+                    ci.high
+                )?;
+            }
+            write!(
+                f,
+                ", d={:.2}, Mann-Whitney p={}, Welch p={}, {}",
+                c.cohens_d,
+                p_value(&c.mann_whitney),
+                p_value(&c.welch),
+                if c.significant {
+                    format!("significant at alpha={}", c.alpha)
+                } else {
+                    format!("not significant at alpha={}", c.alpha)
+                }
+            )?;
+        }
+        for c in &self.chaos {
+            write!(
+                f,
+                "\n{} under chaos: {}/{} recovered, {:.1} disruptions, {:.1} reruns",
+                c.suite, c.recovered, c.iterations, c.disruptions, c.rerun_tests
+            )?;
+            if let (Some(baseline), Some(lost_tests)) = (&c.baseline, c.lost_tests) {
+                write!(f, ", {:.1} tests lost vs {}", lost_tests, baseline)?;
+            }
+            if let (Some(baseline), Some(extra_time)) = (&c.baseline, c.extra_time) {
+                write!(f, ", {:+.1}s vs {}", extra_time, baseline)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn percent(relative: Option<f64>) -> String {
+    relative
+        .map(|r| format!("{:+.1}%", r * 100.0))
+        .unwrap_or_else(|| "n/a".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark_results::{ChaosReport, RunnerExit};
+    use crate::devices::Disturbance;
+    use crate::junit::{TestCase, TestResults, TestStatus};
+    use std::time::{Duration, Instant};
+
+    fn report(iteration: u32, seconds: u64, code: i32) -> ExecutionReport {
+        let start = Instant::now();
+        let exit = RunnerExit {
+            code: Some(code),
+            signal: None,
+        };
+        ExecutionReport::new(
+            "adam".to_owned(),
+            iteration,
+            start,
+            start + Duration::from_secs(seconds),
+            None,
+            Some(exit),
+        )
+    }
+
+    #[test]
+    fn test_invalid_iterations_are_excluded() {
+        let reports = vec![report(1, 100, 0), report(2, 5, 1), report(3, 102, 0)];
+        let summary = RunSummary::new(&reports, &Comparison::default());
+        assert_eq!(1, summary.suites.len());
+        assert_eq!(2, summary.suites[0].duration.samples);
+        assert_eq!(100.0, summary.suites[0].duration.min);
+    }
+
+    #[test]
+    fn test_zero_baseline_round_trips() {
+        let start = Instant::now();
+        let exit = Some(RunnerExit {
+            code: Some(0),
+            signal: None,
+        });
+        let reports: Vec<_> = [("adam", 0), ("adam", 0), ("eve", 1), ("eve", 2)]
+            .iter()
+            .enumerate()
+            .map(|(idx, (suite, seconds))| {
+                ExecutionReport::new(
+                    (*suite).to_owned(),
+                    idx as u32,
+                    start,
+                    start + Duration::from_secs(*seconds),
+                    None,
+                    exit,
+                )
+            })
+            .collect();
+        let summary = RunSummary::new(&reports, &Comparison::default());
+        assert_eq!(None, summary.comparisons[0].relative_difference);
+
+        let json = serde_json::to_string(&summary).expect("Cannot serialize summary");
+        let loaded: RunSummary = serde_json::from_str(&json).expect("Cannot load summary");
+        assert_eq!(summary, loaded);
+        assert!(summary.to_string().contains("(n/a)"));
+    }
+
+    fn tests(names: &[(&str, u32)]) -> TestResults {
+        TestResults {
+            passed: names.len() as u32,
+            failed: 0,
+            ignored: 0,
+            flaky: 0,
+            tests: names
+                .iter()
+                .map(|(name, attempts)| TestCase {
+                    class_name: "com.example.LoginTest".to_owned(),
+                    name: (*name).to_owned(),
+                    duration: Duration::from_secs(1),
+                    attempts: *attempts,
+                    status: TestStatus::Passed,
+                })
+                .collect(),
+        }
+    }
+
+    fn chaos_report(seconds: u64, code: i32, names: &[(&str, u32)]) -> ExecutionReport {
+        let start = Instant::now();
+        let exit = RunnerExit {
+            code: Some(code),
+            signal: None,
+        };
+        let disturbance = |disruption| Disturbance {
+            serial: "localhost:5555".to_owned(),
+            after: Duration::from_secs(10),
+            disruption,
+            error: None,
+        };
+        ExecutionReport::new(
+            "adam-chaos".to_owned(),
+            1,
+            start,
+            start + Duration::from_secs(seconds),
+            Some(tests(names)),
+            Some(exit),
+        )
+        .with_chaos(Some(ChaosReport {
+            baseline: Some("adam".to_owned()),
+            disturbances: vec![
+                disturbance(Disruption::Pause),
+                disturbance(Disruption::Resume),
+            ],
+        }))
+    }
+
+    #[test]
+    fn test_chaos_is_compared_to_baseline() {
+        let start = Instant::now();
+        let baseline = ExecutionReport::new(
+            "adam".to_owned(),
+            1,
+            start,
+            start + Duration::from_secs(100),
+            Some(tests(&[("login", 1), ("logout", 1)])),
+            Some(RunnerExit {
+                code: Some(0),
+                signal: None,
+            }),
+        );
+        let reports = vec![
+            baseline,
+            chaos_report(130, 0, &[("login", 2), ("logout", 1)]),
+            chaos_report(40, 1, &[("login", 1)]),
+        ];
+        let summary = RunSummary::new(&reports, &Comparison::default());
+        let chaos = &summary.chaos[0];
+        assert_eq!("adam-chaos", chaos.suite);
+        assert_eq!(2, chaos.iterations);
+        assert_eq!(1, chaos.recovered);
+        assert_eq!(1.0, chaos.disruptions);
+        assert_eq!(0.5, chaos.rerun_tests);
+        assert_eq!(Some(0.5), chaos.lost_tests);
+        assert_eq!(Some(30.0), chaos.extra_time);
+    }
+}
@@ -1,66 +1,183 @@
-use mozdevice::DeviceInfo;
-
-use crate::adb_server::restart_adb_server;
-use crate::benchmark_results::ExecutionReport;
-use crate::config::{Config, DeviceProvider, TestRunner};
-use crate::devices::{AvailableOnlyProvider, DockerProvider, LocalEmulatorProvider, Provider};
+use crate::adb::AdbClient;
+use crate::apk::package_name;
+use crate::benchmark_results::{ChaosReport, ExecutionReport, Provisioning, RunnerExit};
+use crate::config::{
+    Config, DeviceProvider, FailurePolicy, NetworkProfile, PoolScope, TestRunner, TestSuite,
+};
+use crate::devices::{
+    AvailableOnlyProvider, ChaosSchedule, ContainerEngine, DeviceContext, DevicePool,
+    DeviceReadiness, DeviceTiming, DockerProvider, LocalEmulatorProvider, Provider, ReadinessCheck,
+    ResetStage,
+};
 use crate::file_manager::FileManager;
+use crate::junit::{parse_reports, TestResults};
 use crate::monitoring::{ProcessMonitoring, SystemMonitoring};
 use crate::run_config::RunConfig;
-use crate::testrunners::{ForkRunner, MarathonRunner, Runner, SpoonRunner, SpoonRunner2};
+use crate::shutdown::{interrupted, Shutdown};
+use crate::testrunners::{
+    DeviceSelection, ForkRunner, MarathonRunner, Runner, SimulatedRunner, SpoonRunner, SpoonRunner2,
+};
 use crate::tools::{FlightRecorder, Tool};
 use indicatif::{ProgressBar, ProgressStyle};
-use std::time::Instant;
-
-impl Into<Vec<TestSuiteRunner>> for Config {
-    fn into(self) -> Vec<TestSuiteRunner> {
-        self.test_suites
-            .iter()
-            .map(|suite| {
-                let provider: Box<dyn Provider> = match &suite.device_provider {
-                    DeviceProvider::Docker { image, tag } => Box::new(DockerProvider::new(
-                        image.clone(),
-                        tag.clone(),
-                        suite.emulators,
-                    )),
-                    DeviceProvider::LocalEmulator { name } => {
-                        Box::new(LocalEmulatorProvider::new(name.clone(), suite.emulators))
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+impl From<Config> for Vec<TestSuiteRunner> {
+    fn from(config: Config) -> Self {
+        suite_runners(config, AdbClient::from_env(), &provider)
+    }
+}
+
+fn suite_runners(
+    config: Config,
+    adb: AdbClient,
+    provider: &dyn Fn(&TestSuite) -> Box<dyn Provider>,
+) -> Vec<TestSuiteRunner> {
+    let mut run_pools: Vec<(&TestSuite, SharedPool)> = vec![];
+    config
+        .test_suites
+        .iter()
+        .map(|suite| {
+            // The link is shaped by the provider, so a profile change needs other devices
+            let shared = run_pools.iter().find(|(other, _)| {
+                other.device_provider == suite.device_provider
+                    && other.emulators == suite.emulators
+                    && other.network == suite.network
+                    && other.faults == suite.faults
+            });
+            let devices = match shared {
+                Some((_, pool)) => pool.clone(),
+                None => {
+                    let pool = Rc::new(Mutex::new(DevicePool::new(
+                        provider(suite),
+                        config.device_pool.scope,
+                        adb.clone(),
+                    )));
+                    if config.device_pool.scope == PoolScope::Run {
+                        run_pools.push((suite, pool.clone()));
                     }
-                    DeviceProvider::AvailableOnly => Box::new(AvailableOnlyProvider::new()),
-                };
-                let runner: Box<dyn Runner> = match &suite.test_runner {
-                    TestRunner::Fork {
-                        program,
-                        config_file,
-                    } => Box::new(ForkRunner::new(program.clone(), config_file.clone())),
-                    TestRunner::Marathon {
-                        program,
-                        marathon_file,
-                    } => Box::new(MarathonRunner::new(program.clone(), marathon_file.clone())),
-                    TestRunner::Spoon { jar_file } => Box::new(SpoonRunner::new(jar_file.clone())),
-                    TestRunner::Spoon2 { program } => Box::new(SpoonRunner2::new(program.clone())),
-                };
-                TestSuiteRunner {
-                    name: suite.name.clone(),
-                    apk: self.input.apk.clone(),
-                    test_apk: self.input.test_apk.clone(),
-                    iterations: suite.iterations,
-                    emulators: suite.emulators,
-                    provider,
-                    runner,
+                    pool
                 }
-            })
-            .collect()
+            };
+            devices
+                .try_lock()
+                .expect("Device pool is not in use yet")
+                .add_suite();
+            let runner: Box<dyn Runner> = match &suite.test_runner {
+                TestRunner::Fork {
+                    program,
+                    config_file,
+                } => Box::new(ForkRunner::new(program.clone(), config_file.clone())),
+                TestRunner::Marathon {
+                    program,
+                    marathon_file,
+                } => Box::new(MarathonRunner::new(program.clone(), marathon_file.clone())),
+                TestRunner::Spoon { jar_file } => Box::new(SpoonRunner::new(jar_file.clone())),
+                TestRunner::Spoon2 { program } => Box::new(SpoonRunner2::new(program.clone())),
+                TestRunner::Simulated {
+                    durations,
+                    tests,
+                    failing_tests,
+                    flaky_tests,
+                    exit_code,
+                    seed,
+                } => Box::new(SimulatedRunner::new(
+                    durations.clone(),
+                    *tests,
+                    *failing_tests,
+                    *flaky_tests,
+                    *exit_code,
+                    *seed,
+                )),
+            };
+            TestSuiteRunner {
+                readiness: ReadinessCheck::new(&config.readiness, adb.clone()),
+                name: suite.name.clone(),
+                apk: config.input.apk.clone(),
+                test_apk: config.input.test_apk.clone(),
+                iterations: suite.iterations,
+                emulators: suite.emulators,
+                monitoring_interval: Duration::from_millis(config.monitoring.interval_ms),
+                on_failure: suite.on_failure,
+                devices,
+                reset: ResetStage::new(
+                    config.device_pool.reset.clone(),
+                    suite.reset.clone(),
+                    adb.clone(),
+                ),
+                network: suite.network.clone(),
+                chaos: suite.chaos.clone().map(ChaosSchedule::new),
+                runner,
+            }
+        })
+        .collect()
+}
+
+fn provider(suite: &TestSuite) -> Box<dyn Provider> {
+    match &suite.device_provider {
+        DeviceProvider::Docker {
+            image,
+            tag,
+            concurrency,
+            resources,
+        } => Box::new(
+            DockerProvider::new(
+                ContainerEngine::docker(),
+                image.clone(),
+                tag.clone(),
+                suite.emulators,
+                *concurrency,
+                resources.clone(),
+                suite.network.clone(),
+            )
+            .with_faults(suite.faults.clone()),
+        ),
+        DeviceProvider::Podman {
+            image,
+            tag,
+            concurrency,
+            rootless,
+            resources,
+        } => Box::new(
+            DockerProvider::new(
+                ContainerEngine::podman(*rootless),
+                image.clone(),
+                tag.clone(),
+                suite.emulators,
+                *concurrency,
+                resources.clone(),
+                suite.network.clone(),
+            )
+            .with_faults(suite.faults.clone()),
+        ),
+        DeviceProvider::LocalEmulator { name, options } => Box::new(LocalEmulatorProvider::new(
+            name.clone(),
+            suite.emulators,
+            options.clone(),
+        )),
+        DeviceProvider::AvailableOnly => Box::new(AvailableOnlyProvider::new()),
     }
 }
 
+// Suites run one after another, so a pool is never locked by two of them at once
+type SharedPool = Rc<Mutex<DevicePool>>;
+
 pub struct TestSuiteRunner {
     name: String,
     apk: String,
     test_apk: String,
     iterations: u32,
     emulators: u32,
-    provider: Box<dyn Provider>,
+    monitoring_interval: Duration,
+    on_failure: FailurePolicy,
+    devices: SharedPool,
+    readiness: ReadinessCheck,
+    reset: ResetStage,
+    network: Option<NetworkProfile>,
+    chaos: Option<ChaosSchedule>,
     runner: Box<dyn Runner>,
 }
 
@@ -69,7 +186,8 @@ impl TestSuiteRunner {
         &mut self,
         idx: u32,
         run_config: &RunConfig,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+        shutdown: &Shutdown,
+    ) -> Result<RunnerExit, Box<dyn std::error::Error>> {
         let spinner_style = ProgressStyle::default_spinner()
             .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
             .template("{prefix:.bold.dim} {spinner} {wide_msg}");
@@ -84,7 +202,41 @@ impl TestSuiteRunner {
             self.name.clone(),
             idx,
         );
+        let context = DeviceContext {
+            run_uuid: run_config.uuid,
+            suite: self.name.clone(),
+            iteration: idx,
+            log_dir: file_manager.get_devices_log_dir()?,
+        };
+        let tool = FlightRecorder::default();
+        let mut tool_started = false;
+
+        let iteration =
+            self.execute_iteration(&context, &file_manager, &tool, &mut tool_started, &spinner);
+        let result = tokio::select! {
+            result = iteration => result,
+            _ = shutdown.requested() => Err(interrupted()),
+        };
+
+        // Devices have to go away whatever happened above
+        spinner.set_message("Terminate");
+        let started_tool = if tool_started { Some(&tool) } else { None };
+        let teardown = self
+            .teardown(&file_manager, started_tool, result.is_err(), shutdown)
+            .await;
+        let exit = result?;
+        teardown?;
+        Ok(exit)
+    }
 
+    async fn execute_iteration(
+        &mut self,
+        context: &DeviceContext,
+        file_manager: &FileManager,
+        tool: &FlightRecorder,
+        tool_started: &mut bool,
+        spinner: &ProgressBar,
+    ) -> Result<RunnerExit, Box<dyn std::error::Error>> {
         let working_dir = file_manager.get_working_dir()?;
 
         file_manager.prepare_working_dir(
@@ -94,59 +246,450 @@ impl TestSuiteRunner {
         )?;
 
         spinner.set_message("Prepare devices");
-        restart_adb_server().await?;
-        self.provider.prepare().await?;
-
-        spinner.set_message("Connect devices");
-        self.provider.connect().await?;
+        let provisioning_start = Instant::now();
+        let mut devices = self.devices.lock().await;
+        devices.acquire(context).await?;
 
         spinner.set_message("Wait for devices");
-        wait_for_devices(self.emulators);
+        let owned = devices.serials();
+        let mut ready = self
+            .readiness
+            .wait(owned.as_deref(), self.emulators)
+            .await?;
+        let device_timings = with_boot_timings(devices.device_timings(), &ready.devices);
 
-        let tool = FlightRecorder::default();
-        let results_dir = file_manager.get_tools_results_dir(&tool.get_name())?;
+        // Freshly booted devices have nothing to reset
+        if devices.is_persisted() {
+            let reset = &self.reset;
+            spinner.set_message("Reset devices");
+            let serials: Vec<String> = ready.devices.iter().map(|d| d.serial.clone()).collect();
+            let packages = if reset.needs_packages() {
+                vec![
+                    package_name(Path::new(&self.apk))?,
+                    package_name(Path::new(&self.test_apk))?,
+                ]
+            } else {
+                vec![]
+            };
+            if reset.run(&serials, &packages).await? {
+                spinner.set_message("Wait for devices after reset");
+                devices.reconnect().await?;
+                ready = self
+                    .readiness
+                    .wait(owned.as_deref(), self.emulators)
+                    .await?;
+            }
+        }
+        let provisioning = Provisioning::new(
+            provisioning_start.elapsed(),
+            device_timings,
+            devices.is_reused(),
+        );
+        drop(devices);
+        let foreign = ready.foreign;
+        let devices = owned.map(|serials| DeviceSelection::new(serials, foreign));
+
+        let results_dir = file_manager.get_tools_results_dir(tool.get_name())?;
         tool.start().await?;
+        *tool_started = true;
 
         spinner.set_message("Start monitoring");
-        let process_monitoring = ProcessMonitoring::new();
-        let system_monitoring = SystemMonitoring::new();
-        process_monitoring.start();
+        let mut process_monitoring = ProcessMonitoring::new(self.monitoring_interval);
+        let mut system_monitoring = SystemMonitoring::new(self.monitoring_interval);
         system_monitoring.start();
 
         spinner.set_message("Run tests");
+        self.devices.lock().await.start_faults();
         let test_run_start = Instant::now();
-        let result = self
+        let result = match self
             .runner
-            .start(tool.jvm_args(results_dir), working_dir)
-            .await;
+            .start(
+                tool.jvm_args(results_dir),
+                working_dir.clone(),
+                devices.as_ref(),
+            )
+            .await
+        {
+            Ok(mut child) => {
+                if let Some(pid) = child.id() {
+                    process_monitoring.start(pid);
+                }
+                let status = match &mut self.chaos {
+                    Some(chaos) => {
+                        let wait = child.wait();
+                        tokio::pin!(wait);
+                        tokio::select! {
+                            status = &mut wait => status,
+                            _ = chaos.run(&self.devices) => wait.await,
+                        }
+                    }
+                    None => child.wait().await,
+                };
+                status.map_err(|e| e.into())
+            }
+            Err(e) => Err(e),
+        };
         let test_run_end = Instant::now();
-        let report = ExecutionReport::new(self.name.clone(), idx, test_run_start, test_run_end);
+        let faults = self.devices.lock().await.stop_faults();
+        let chaos = match &mut self.chaos {
+            Some(chaos) => Some(ChaosReport {
+                baseline: chaos.baseline(),
+                disturbances: chaos.settle(&self.devices).await,
+            }),
+            None => None,
+        };
+        let process_report = process_monitoring.stop().await?;
+        let system_report = system_monitoring.stop().await?;
+        let junit_reports = self.runner.junit_reports(&working_dir);
+        let (tests, junit_error) = test_results(&junit_reports);
+        if let Some(error) = &junit_error {
+            spinner.println(format!(
+                "[{}] Iteration #{}: cannot parse JUnit reports, keeping it without test results: {}",
+                self.name, context.iteration, error
+            ));
+        }
+        let exit = result.as_ref().ok().map(|status| RunnerExit::new(*status));
+        let report = ExecutionReport::new(
+            self.name.clone(),
+            context.iteration,
+            test_run_start,
+            test_run_end,
+            tests,
+            exit,
+        )
+        .with_junit_error(junit_error)
+        .with_provisioning(provisioning)
+        .with_network(self.network.clone())
+        .with_faults(faults)
+        .with_chaos(chaos);
 
         file_manager.save_execution_report(report).await?;
+        file_manager
+            .save_system_monitoring_report(system_report)
+            .await?;
+        file_manager
+            .save_process_monitoring_report(process_report)
+            .await?;
+        Ok(RunnerExit::new(result?))
+    }
 
-        tool.stop().await?;
+    // Keeps going after a failed step so one error does not leak the remaining resources. The tool
+    // is None when it never started
+    async fn teardown(
+        &mut self,
+        file_manager: &FileManager,
+        tool: Option<&FlightRecorder>,
+        failed: bool,
+        shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let tool_result = match tool {
+            Some(tool) => tool.stop().await,
+            None => Ok(()),
+        };
+        let clean_up_result = file_manager.clean_up_working_dir();
+        let mut devices = self.devices.lock().await;
+        let release_result = tokio::select! {
+            result = devices.release(failed) => result,
+            _ = shutdown.forced() => Ok(()),
+        };
+        // A second signal or a failed terminate leaves devices behind, kill them right away
+        if shutdown.is_forced() || release_result.is_err() {
+            devices.kill();
+        }
+        tool_result?;
+        clean_up_result?;
+        release_result
+    }
 
-        spinner.set_message("Terminate");
-        file_manager.clean_up_working_dir()?;
-        self.provider.terminate().await?;
-        result
+    async fn finish_devices(
+        &mut self,
+        shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut devices = self.devices.lock().await;
+        let finish_result = tokio::select! {
+            result = devices.finish_suite() => result,
+            _ = shutdown.forced() => Ok(()),
+        };
+        if shutdown.is_forced() || finish_result.is_err() {
+            devices.kill();
+        }
+        finish_result
     }
 
     pub async fn start(
         &mut self,
         run_config: &RunConfig,
+        shutdown: &Shutdown,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let result = self.run_iterations(run_config, shutdown).await;
+        let finish_result = self.finish_devices(shutdown).await;
+        result?;
+        finish_result
+    }
+
+    async fn run_iterations(
+        &mut self,
+        run_config: &RunConfig,
+        shutdown: &Shutdown,
     ) -> Result<(), Box<dyn std::error::Error>> {
         for i in 1..self.iterations + 1 {
-            self.run_iteration(i, run_config).await?;
+            let mut retries = 0;
+            loop {
+                let exit = self.run_iteration(i, run_config, shutdown).await?;
+                if exit.success() {
+                    break;
+                }
+                match self.on_failure {
+                    FailurePolicy::Retry { attempts } if retries < attempts => retries += 1,
+                    FailurePolicy::Abort => {
+                        return Err(Box::new(Error::other(format!(
+                            "Runner of suite {} failed on iteration {}: {}",
+                            self.name, i, exit
+                        ))));
+                    }
+                    // The report of the last attempt stays on disk marked as invalid
+                    _ => break,
+                }
+            }
         }
         Ok(())
     }
 }
 
-fn wait_for_devices(expected: u32) {
-    let host = mozdevice::Host::default();
-    let mut devices: Vec<DeviceInfo> = host.devices().expect("Cannot get all devices");
-    while devices.len() < expected as usize {
-        devices = host.devices().expect("Cannot get all devices");
+// A runner which crashed or got killed may leave a truncated report behind, losing its test
+// results is better than aborting the benchmark
+fn test_results(paths: &[PathBuf]) -> (Option<TestResults>, Option<String>) {
+    if paths.is_empty() {
+        return (None, None);
+    }
+    match parse_reports(paths) {
+        Ok(results) => (Some(results), None),
+        Err(e) => (None, Some(e.to_string())),
+    }
+}
+
+// Providers don't know when a device finished booting, the readiness check does
+fn with_boot_timings(
+    mut timings: Vec<DeviceTiming>,
+    ready: &[DeviceReadiness],
+) -> Vec<DeviceTiming> {
+    for device in ready {
+        match timings.iter_mut().find(|t| t.serial == device.serial) {
+            Some(timing) => timing.boot_completed = Some(device.ready_after),
+            None => {
+                let mut timing = DeviceTiming::new(device.serial.clone());
+                timing.boot_completed = Some(device.ready_after);
+                timings.push(timing);
+            }
+        }
+    }
+    timings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adb::{FakeAdbServer, FakeDevice};
+    use crate::config::Comparison;
+    use crate::devices::SimulatedProvider;
+    use crate::run_directory::RunDirectory;
+    use crate::summary::RunSummary;
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    const SERIALS: [&str; 2] = ["emulator-5554", "emulator-5556"];
+
+    fn config(working_dir: &Path, suites: &str) -> Config {
+        let apk = working_dir.join("app.apk");
+        let test_apk = working_dir.join("app-test.apk");
+        std::fs::write(&apk, b"apk").expect("Cannot write apk");
+        std::fs::write(&test_apk, b"test apk").expect("Cannot write test apk");
+        let yaml = format!(
+            r#"
+input:
+  apk: {}
+  test_apk: {}
+monitoring:
+  interval_ms: 50
+readiness:
+  timeout_secs: 5
+  initial_backoff_ms: 10
+  max_backoff_ms: 50
+test_suites:
+{}"#,
+            apk.display(),
+            test_apk.display(),
+            suites
+        );
+        serde_yaml::from_str(&yaml).expect("Config does not parse")
+    }
+
+    const SUITES: &str = r#"
+  - name: fast
+    iterations: 3
+    emulators: 2
+    device_provider: available_only
+    test_runner:
+      simulated:
+        durations:
+          scripted: [100, 150, 120]
+        tests: 5
+        flaky_tests: 1
+  - name: slow
+    iterations: 2
+    emulators: 2
+    device_provider: available_only
+    test_runner:
+      simulated:
+        durations:
+          normal:
+            mean_ms: 300
+            std_dev_ms: 20
+        failing_tests: 1
+        seed: 7
+"#;
+
+    #[test]
+    fn test_malformed_junit_report_is_kept_as_error() {
+        let dir = std::env::temp_dir().join(format!("junit-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("Cannot create report dir");
+        let truncated = dir.join("TEST-truncated.xml");
+        std::fs::write(
+            &truncated,
+            "<testsuite name=\"killed\"><testcase classname=\"com.example.Test\" na",
+        )
+        .expect("Cannot write report");
+        let (tests, error) = test_results(&[truncated]);
+        std::fs::remove_dir_all(&dir).expect("Cannot remove report dir");
+
+        assert_eq!(None, tests);
+        assert!(error.is_some());
+        assert_eq!((None, None), test_results(&[]));
+    }
+
+    #[tokio::test]
+    async fn test_connected_devices_are_reset_between_iterations() {
+        let working_dir = std::env::temp_dir().join(format!("reset-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&working_dir).expect("Cannot create working dir");
+        let server = FakeAdbServer::start(vec![FakeDevice::new("emulator-5554")]).await;
+        let suite = r#"
+  - name: reset
+    iterations: 2
+    emulators: 1
+    device_provider: available_only
+    test_runner:
+      simulated:
+        durations:
+          scripted: [10]
+    reset:
+      sdcard_paths:
+        - /sdcard/marathon
+"#;
+        let runners = suite_runners(config(&working_dir, suite), server.client(), &provider);
+        let run_config = RunConfig::new(Uuid::new_v4(), working_dir.clone());
+        let shutdown = Shutdown::listen();
+        for mut runner in runners {
+            runner
+                .start(&run_config, &shutdown)
+                .await
+                .expect("Suite failed");
+        }
+        std::fs::remove_dir_all(&working_dir).expect("Cannot remove working dir");
+
+        // Only the second iteration found used devices
+        let resets: Vec<String> = server
+            .commands("emulator-5554")
+            .into_iter()
+            .filter(|command| !command.starts_with("getprop") && !command.starts_with("pm path"))
+            .collect();
+        assert_eq!(
+            vec![
+                "logcat -c >/dev/null 2>&1; echo $?",
+                "rm -rf '/sdcard/marathon' >/dev/null 2>&1; echo $?"
+            ],
+            resets
+        );
+    }
+
+    #[tokio::test]
+    async fn test_suites_run_end_to_end() {
+        let working_dir = std::env::temp_dir().join(format!("pipeline-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&working_dir).expect("Cannot create working dir");
+        let server =
+            FakeAdbServer::start(SERIALS.iter().map(|s| FakeDevice::new(s)).collect()).await;
+        let lifecycle = Arc::new(std::sync::Mutex::new(vec![]));
+        let runners = suite_runners(config(&working_dir, SUITES), server.client(), &|_| {
+            Box::new(SimulatedProvider::new(
+                SERIALS.iter().map(|s| s.to_string()).collect(),
+                Duration::from_millis(20),
+                lifecycle.clone(),
+            ))
+        });
+        let run_config = RunConfig::new(Uuid::new_v4(), working_dir.clone());
+        let shutdown = Shutdown::listen();
+        for mut runner in runners {
+            runner
+                .start(&run_config, &shutdown)
+                .await
+                .expect("Suite failed");
+        }
+
+        let run_dir = RunDirectory::new(&working_dir, run_config.uuid);
+        assert_eq!(
+            vec!["fast", "slow"],
+            run_dir.suites().expect("Cannot list suites")
+        );
+        assert_eq!(
+            vec![1, 2, 3],
+            run_dir.iterations("fast").expect("Cannot list iterations")
+        );
+        assert_eq!(
+            vec![1, 2],
+            run_dir.iterations("slow").expect("Cannot list iterations")
+        );
+        for (suite, iteration) in &[("fast", 1), ("slow", 2)] {
+            let dir = run_dir.iteration_dir(suite, *iteration);
+            assert!(!dir.join("application.apk").exists());
+            assert!(!dir.join("test_application.apk").exists());
+            assert!(dir.join("execution_report.json").exists());
+            assert!(dir.join("system_monitoring.json").exists());
+        }
+
+        let reports = run_dir
+            .load_execution_reports()
+            .expect("Cannot load reports");
+        assert_eq!(5, reports.len());
+        for report in &reports {
+            assert!(report.is_valid());
+            let tests = report.tests().expect("No test results");
+            match report.suite_name() {
+                "fast" => assert_eq!((4, 0, 1), (tests.passed, tests.failed, tests.flaky)),
+                _ => assert_eq!((9, 1, 0), (tests.passed, tests.failed, tests.flaky)),
+            }
+        }
+        let summary = RunSummary::new(&reports, &Comparison::default());
+        assert_eq!(2, summary.suites.len());
+        assert_eq!(1, summary.comparisons.len());
+        assert_eq!("fast", summary.comparisons[0].baseline);
+        assert!(summary.comparisons[0].mean_difference > 0.0);
+
+        // Every iteration got its own devices and gave them back
+        let lifecycle = lifecycle.lock().expect("Cannot lock lifecycle");
+        assert_eq!(
+            vec![
+                "prepare fast 1",
+                "terminate",
+                "prepare fast 2",
+                "terminate",
+                "prepare fast 3",
+                "terminate",
+                "prepare slow 1",
+                "terminate",
+                "prepare slow 2",
+                "terminate",
+            ],
+            *lifecycle
+        );
+        std::fs::remove_dir_all(&working_dir).expect("Cannot remove working dir");
     }
 }
@@ -1,10 +1,11 @@
-use crate::testrunners::Runner;
+use crate::junit::find_xml_files;
+use crate::testrunners::{DeviceSelection, Runner};
 use async_trait::async_trait;
 use std::error::Error;
 use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use tokio::process::Command;
+use tokio::process::{Child, Command};
 
 // Fork runner https://github.com/shazam/fork
 pub struct ForkRunner {
@@ -21,13 +22,28 @@ impl ForkRunner {
     }
 }
 
+// Fork can only exclude devices, so everything the provider doesn't own goes on that list
+fn exclude_serials(config_file: &Path, serials: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut config: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(config_file)?)?;
+    let mut excluded: Vec<serde_json::Value> = config["excludedSerials"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    excluded.extend(serials.iter().map(|serial| serial.as_str().into()));
+    config["excludedSerials"] = serde_json::Value::Array(excluded);
+    std::fs::write(config_file, serde_json::to_string_pretty(&config)?)?;
+    Ok(())
+}
+
 #[async_trait]
 impl Runner for ForkRunner {
     async fn start(
         &self,
         jvm_args: Vec<String>,
         working_dir: PathBuf,
-    ) -> Result<(), Box<dyn Error>> {
+        devices: Option<&DeviceSelection>,
+    ) -> Result<Child, Box<dyn Error>> {
         let config_path = Path::new(&self.config_file);
         let config_file_name = config_path
             .file_name()
@@ -40,21 +56,38 @@ impl Runner for ForkRunner {
             .expect("Cannot create stderr log file");
         let stdout = File::create(working_dir.join("runner_stdout.txt"))
             .expect("Cannot create stdout log file");
-        Command::new(&self.program)
+        let mut command = Command::new(&self.program);
+        command
             .env("FORK_RUNNER_OPTS", jvm_args.join(" "))
             .args(vec!["--apk", "application.apk"])
             .args(vec!["--test-apk", "test_application.apk"])
             .args(vec!["--config", &config_file_name])
-            .current_dir(working_dir)
+            .current_dir(&working_dir)
             .stdout(Stdio::from(stdout))
             .stderr(Stdio::from(stderr))
-            .spawn()?
-            .wait()
-            .await?;
-        Ok(())
+            .kill_on_drop(true);
+        if let Some(devices) = devices {
+            exclude_serials(&working_dir.join(&config_file_name), &devices.others)?;
+            devices.apply_android_serial(&mut command);
+        }
+        Ok(command.spawn()?)
     }
 
     fn required_files(&self) -> Vec<String> {
         vec![self.config_file.clone()]
     }
+
+    fn junit_reports(&self, working_dir: &Path) -> Vec<PathBuf> {
+        let config_file = working_dir.join(
+            Path::new(&self.config_file)
+                .file_name()
+                .expect("Cannot get filename"),
+        );
+        let output_dir = std::fs::read_to_string(config_file)
+            .ok()
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .and_then(|config| config.get("baseOutputDir")?.as_str().map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from("fork-output"));
+        find_xml_files(&working_dir.join(output_dir))
+    }
 }
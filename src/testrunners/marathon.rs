@@ -1,10 +1,11 @@
-use crate::testrunners::Runner;
+use crate::junit::find_xml_files;
+use crate::testrunners::{DeviceSelection, Runner};
 use async_trait::async_trait;
 use std::error::Error;
 use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use tokio::process::Command;
+use tokio::process::{Child, Command};
 
 // Marathon  https://github.com/Malinskiy/marathon
 pub struct MarathonRunner {
@@ -21,13 +22,44 @@ impl MarathonRunner {
     }
 }
 
+// Rewrites the copy of the Marathonfile in the working dir so marathon skips every other device
+fn include_serials(marathon_file: &Path, serials: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut config: serde_yaml::Value =
+        serde_yaml::from_str(&std::fs::read_to_string(marathon_file)?)?;
+    let regexes = serials
+        .iter()
+        .map(|serial| serde_yaml::Value::from(format!("^{}$", escape_regex(serial))))
+        .collect();
+    if let serde_yaml::Value::Mapping(mapping) = &mut config {
+        mapping.insert(
+            "includeSerialRegexes".into(),
+            serde_yaml::Value::Sequence(regexes),
+        );
+    }
+    std::fs::write(marathon_file, serde_yaml::to_string(&config)?)?;
+    Ok(())
+}
+
+fn escape_regex(value: &str) -> String {
+    value
+        .chars()
+        .flat_map(|c| {
+            let escape = "\\.+*?()|[]{}^$".contains(c);
+            std::iter::once('\\')
+                .filter(move |_| escape)
+                .chain(std::iter::once(c))
+        })
+        .collect()
+}
+
 #[async_trait]
 impl Runner for MarathonRunner {
     async fn start(
         &self,
         jvm_args: Vec<String>,
         working_dir: PathBuf,
-    ) -> Result<(), Box<dyn Error>> {
+        devices: Option<&DeviceSelection>,
+    ) -> Result<Child, Box<dyn Error>> {
         let config_path = Path::new(&self.marathon_file);
         let config_file_name = config_path
             .file_name()
@@ -41,19 +73,65 @@ impl Runner for MarathonRunner {
         let stdout = File::create(working_dir.join("runner_stdout.txt"))
             .expect("Cannot create stdout log file");
 
-        Command::new(&self.program)
+        let mut command = Command::new(&self.program);
+        command
             .env("MARATHON_OPTS", jvm_args.join(" ")) //
             .args(vec!["-m", &config_file_name])
             .stdout(Stdio::from(stdout))
             .stderr(Stdio::from(stderr))
-            .current_dir(working_dir)
-            .spawn()?
-            .wait()
-            .await?;
-        Ok(())
+            .current_dir(&working_dir)
+            .kill_on_drop(true);
+        if let Some(devices) = devices {
+            include_serials(&working_dir.join(&config_file_name), &devices.serials)?;
+            devices.apply_android_serial(&mut command);
+        }
+        Ok(command.spawn()?)
     }
 
     fn required_files(&self) -> Vec<String> {
         vec![self.marathon_file.clone()]
     }
+
+    fn junit_reports(&self, working_dir: &Path) -> Vec<PathBuf> {
+        let marathon_file = working_dir.join(
+            Path::new(&self.marathon_file)
+                .file_name()
+                .expect("Cannot get filename"),
+        );
+        let output_dir = std::fs::read_to_string(marathon_file)
+            .ok()
+            .and_then(|content| serde_yaml::from_str::<serde_yaml::Value>(&content).ok())
+            .and_then(|config| config.get("outputDir")?.as_str().map(PathBuf::from))
+            .unwrap_or_else(|| PathBuf::from("output"));
+        find_xml_files(&working_dir.join(output_dir).join("tests"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_include_serials() {
+        let marathon_file =
+            std::env::temp_dir().join(format!("Marathonfile-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&marathon_file, "name: adam\noutputDir: out\n").expect("Cannot write");
+        include_serials(&marathon_file, &["localhost:40001".to_owned()])
+            .expect("Cannot include serials");
+        let config: serde_yaml::Value =
+            serde_yaml::from_str(&std::fs::read_to_string(&marathon_file).expect("Cannot read"))
+                .expect("Invalid yaml");
+        std::fs::remove_file(marathon_file).expect("Cannot remove");
+        assert_eq!(
+            Some("^localhost:40001$"),
+            config["includeSerialRegexes"][0].as_str()
+        );
+        assert_eq!(Some("out"), config["outputDir"].as_str());
+    }
+
+    #[test]
+    fn test_escape_regex() {
+        assert_eq!("emulator-5554", escape_regex("emulator-5554"));
+        assert_eq!("10\\.0\\.2\\.2:5555", escape_regex("10.0.2.2:5555"));
+    }
 }
@@ -1,12 +1,14 @@
 mod fork;
 mod marathon;
 mod runner;
+mod simulated;
 mod spoon;
 mod spoon2;
 
-pub use runner::Runner;
+pub use runner::{DeviceSelection, Runner};
 
 pub use fork::ForkRunner;
 pub use marathon::MarathonRunner;
+pub use simulated::SimulatedRunner;
 pub use spoon::SpoonRunner;
 pub use spoon2::SpoonRunner2;
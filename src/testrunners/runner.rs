@@ -1,12 +1,38 @@
 use async_trait::async_trait;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use tokio::process::{Child, Command};
+
+// Devices the runner may use. Others are online too but belong to someone else
+pub struct DeviceSelection {
+    pub serials: Vec<String>,
+    pub others: Vec<String>,
+}
+
+impl DeviceSelection {
+    pub fn new(serials: Vec<String>, others: Vec<String>) -> Self {
+        DeviceSelection { serials, others }
+    }
+
+    // adb honours ANDROID_SERIAL only for a single device
+    pub fn apply_android_serial(&self, command: &mut Command) {
+        if let [serial] = self.serials.as_slice() {
+            command.env("ANDROID_SERIAL", serial);
+        }
+    }
+}
 
 #[async_trait]
 pub trait Runner {
+    // Spawns the runner, the caller owns the child and waits for it to finish.
+    // The child is killed on drop so an interrupted iteration does not leave it running.
+    // Without a device selection the runner picks up every device adb knows about
     async fn start(
         &self,
         jvm_args: Vec<String>,
         working_dir: PathBuf,
-    ) -> Result<(), Box<dyn std::error::Error>>;
+        devices: Option<&DeviceSelection>,
+    ) -> Result<Child, Box<dyn std::error::Error>>;
     fn required_files(&self) -> Vec<String>;
+    // JUnit XML files the runner wrote into the working dir
+    fn junit_reports(&self, working_dir: &Path) -> Vec<PathBuf>;
 }
@@ -0,0 +1,202 @@
+use crate::config::SimulatedDurations;
+use crate::junit::find_xml_files;
+use crate::random::XorShift;
+use crate::testrunners::{DeviceSelection, Runner};
+use async_trait::async_trait;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+
+const OUTPUT_DIR: &str = "simulated-output";
+const CLASS_NAME: &str = "com.example.SimulatedTest";
+
+// Lets the whole pipeline run without Android tooling. The child only sleeps, the JUnit report
+// is written up front
+pub struct SimulatedRunner {
+    durations: SimulatedDurations,
+    tests: u32,
+    failing_tests: u32,
+    flaky_tests: u32,
+    exit_code: i32,
+    state: Mutex<SimulationState>,
+}
+
+struct SimulationState {
+    iteration: usize,
+    rng: XorShift,
+}
+
+impl SimulatedRunner {
+    pub fn new(
+        durations: SimulatedDurations,
+        tests: u32,
+        failing_tests: u32,
+        flaky_tests: u32,
+        exit_code: i32,
+        seed: Option<u64>,
+    ) -> Self {
+        SimulatedRunner {
+            durations,
+            tests,
+            failing_tests,
+            flaky_tests,
+            exit_code,
+            state: Mutex::new(SimulationState {
+                iteration: 0,
+                rng: seed.map(XorShift::new).unwrap_or_else(XorShift::from_time),
+            }),
+        }
+    }
+
+    fn next_duration(&self) -> Duration {
+        let mut state = self.state.lock().expect("Simulation is poisoned");
+        let iteration = state.iteration;
+        state.iteration += 1;
+        let millis = match &self.durations {
+            SimulatedDurations::Scripted(durations) if durations.is_empty() => 0.0,
+            SimulatedDurations::Scripted(durations) => {
+                durations[iteration % durations.len()] as f64
+            }
+            // Box-Muller transform
+            SimulatedDurations::Normal {
+                mean_ms,
+                std_dev_ms,
+            } => {
+                let u1 = 1.0 - state.rng.unit();
+                let u2 = state.rng.unit();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                (*mean_ms as f64 + z * *std_dev_ms as f64).max(0.0)
+            }
+        };
+        Duration::from_millis(millis as u64)
+    }
+
+    // Failing tests come first, then the flaky ones which show up once failed and once passed
+    fn junit_report(&self, duration: Duration) -> String {
+        let time = duration.as_secs_f64() / self.tests.max(1) as f64;
+        let mut cases = String::new();
+        for idx in 0..self.tests {
+            let failure = format!(
+                "<testcase classname=\"{}\" name=\"test{}\" time=\"{:.3}\"><failure message=\"simulated\"/></testcase>\n",
+                CLASS_NAME, idx, time
+            );
+            let success = format!(
+                "<testcase classname=\"{}\" name=\"test{}\" time=\"{:.3}\"/>\n",
+                CLASS_NAME, idx, time
+            );
+            if idx < self.failing_tests {
+                cases.push_str(&failure);
+            } else if idx < self.failing_tests + self.flaky_tests {
+                cases.push_str(&failure);
+                cases.push_str(&success);
+            } else {
+                cases.push_str(&success);
+            }
+        }
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuite name=\"simulated\" time=\"{:.3}\">\n{}</testsuite>\n",
+            duration.as_secs_f64(),
+            cases
+        )
+    }
+}
+
+#[async_trait]
+impl Runner for SimulatedRunner {
+    async fn start(
+        &self,
+        _jvm_args: Vec<String>,
+        working_dir: PathBuf,
+        _devices: Option<&DeviceSelection>,
+    ) -> Result<Child, Box<dyn Error>> {
+        let duration = self.next_duration();
+        let output_dir = working_dir.join(OUTPUT_DIR);
+        std::fs::create_dir_all(&output_dir)?;
+        std::fs::write(
+            output_dir.join("TEST-simulated.xml"),
+            self.junit_report(duration),
+        )?;
+        let script = format!(
+            "sleep {:.3}; exit {}",
+            duration.as_secs_f64(),
+            self.exit_code
+        );
+        let child = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .current_dir(working_dir)
+            .kill_on_drop(true)
+            .spawn()?;
+        Ok(child)
+    }
+
+    fn required_files(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn junit_reports(&self, working_dir: &Path) -> Vec<PathBuf> {
+        find_xml_files(&working_dir.join(OUTPUT_DIR))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::junit::{parse_reports, TestStatus};
+
+    #[test]
+    fn test_scripted_durations_repeat() {
+        let runner = SimulatedRunner::new(
+            SimulatedDurations::Scripted(vec![100, 200]),
+            1,
+            0,
+            0,
+            0,
+            None,
+        );
+        let durations: Vec<_> = (0..3).map(|_| runner.next_duration()).collect();
+        assert_eq!(
+            vec![
+                Duration::from_millis(100),
+                Duration::from_millis(200),
+                Duration::from_millis(100)
+            ],
+            durations
+        );
+    }
+
+    #[test]
+    fn test_junit_report_parses() {
+        let runner = SimulatedRunner::new(
+            SimulatedDurations::Normal {
+                mean_ms: 1000,
+                std_dev_ms: 100,
+            },
+            4,
+            1,
+            1,
+            0,
+            Some(3),
+        );
+        let dir = std::env::temp_dir().join(format!("simulated-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("TEST-simulated.xml");
+        std::fs::write(&path, runner.junit_report(Duration::from_secs(4))).unwrap();
+        let results = parse_reports(&[path]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let results = results.expect("Report does not parse");
+        assert_eq!((2, 1, 1), (results.passed, results.failed, results.flaky));
+        let flaky = results
+            .tests
+            .iter()
+            .find(|test| test.status == TestStatus::Flaky)
+            .expect("No flaky test");
+        assert_eq!(2, flaky.attempts);
+    }
+}
@@ -1,10 +1,11 @@
-use crate::testrunners::Runner;
+use crate::junit::find_xml_files;
+use crate::testrunners::{DeviceSelection, Runner};
 use async_trait::async_trait;
 use std::error::Error;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use tokio::process::Command;
+use tokio::process::{Child, Command};
 
 // Spoon https://github.com/square/spoon
 // Use latest master branch build
@@ -24,12 +25,14 @@ impl Runner for SpoonRunner {
         &self,
         jvm_args: Vec<String>,
         working_dir: PathBuf,
-    ) -> Result<(), Box<dyn Error>> {
+        devices: Option<&DeviceSelection>,
+    ) -> Result<Child, Box<dyn Error>> {
         let stderr = File::create(working_dir.join("runner_stderr.txt"))
             .expect("Cannot create stderr log file");
         let stdout = File::create(working_dir.join("runner_stdout.txt"))
             .expect("Cannot create stdout log file");
-        Command::new("java")
+        let mut command = Command::new("java");
+        command
             .args(jvm_args)
             .args(vec!["-jar", &self.jar_file])
             .args(vec!["--apk", "application.apk"])
@@ -38,13 +41,21 @@ impl Runner for SpoonRunner {
             .stdout(Stdio::from(stdout))
             .stderr(Stdio::from(stderr))
             .current_dir(working_dir)
-            .spawn()?
-            .wait()
-            .await?;
-        Ok(())
+            .kill_on_drop(true);
+        if let Some(devices) = devices {
+            for serial in &devices.serials {
+                command.args(vec!["--serial", serial]);
+            }
+            devices.apply_android_serial(&mut command);
+        }
+        Ok(command.spawn()?)
     }
 
     fn required_files(&self) -> Vec<String> {
         vec![]
     }
+
+    fn junit_reports(&self, working_dir: &Path) -> Vec<PathBuf> {
+        find_xml_files(&working_dir.join("spoon-output").join("junit-reports"))
+    }
 }
@@ -1,10 +1,11 @@
-use crate::testrunners::Runner;
+use crate::junit::find_xml_files;
+use crate::testrunners::{DeviceSelection, Runner};
 use async_trait::async_trait;
 use std::error::Error;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use tokio::process::Command;
+use tokio::process::{Child, Command};
 
 // Spoon https://github.com/square/spoon
 // Use latest master branch build
@@ -24,25 +25,35 @@ impl Runner for SpoonRunner2 {
         &self,
         jvm_args: Vec<String>,
         working_dir: PathBuf,
-    ) -> Result<(), Box<dyn Error>> {
+        devices: Option<&DeviceSelection>,
+    ) -> Result<Child, Box<dyn Error>> {
         let stderr = File::create(working_dir.join("runner_stderr.txt"))
             .expect("Cannot create stderr log file");
         let stdout = File::create(working_dir.join("runner_stdout.txt"))
             .expect("Cannot create stdout log file");
-        Command::new(&self.program)
+        let mut command = Command::new(&self.program);
+        command
             .env("SPOON_RUNNER_OPTS", jvm_args.join(" "))
             .args(vec!["test_application.apk", "application.apk"])
             .arg("--shard")
             .stdout(Stdio::from(stdout))
             .stderr(Stdio::from(stderr))
             .current_dir(working_dir)
-            .spawn()?
-            .wait()
-            .await?;
-        Ok(())
+            .kill_on_drop(true);
+        if let Some(devices) = devices {
+            for serial in &devices.serials {
+                command.args(vec!["--serial", serial]);
+            }
+            devices.apply_android_serial(&mut command);
+        }
+        Ok(command.spawn()?)
     }
 
     fn required_files(&self) -> Vec<String> {
         vec![]
     }
+
+    fn junit_reports(&self, working_dir: &Path) -> Vec<PathBuf> {
+        find_xml_files(&working_dir.join("spoon-output"))
+    }
 }
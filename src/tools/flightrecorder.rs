@@ -3,14 +3,9 @@ use async_trait::async_trait;
 use std::error::Error;
 use std::path::PathBuf;
 
+#[derive(Default)]
 pub struct FlightRecorder {}
 
-impl Default for FlightRecorder {
-    fn default() -> Self {
-        FlightRecorder {}
-    }
-}
-
 #[async_trait]
 impl Tool for FlightRecorder {
     async fn start(&self) -> Result<(), Box<dyn Error>> {